@@ -0,0 +1,8 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // recorded in the provenance of packed blocks
+    println!(
+        "cargo:rustc-env=ANVIL_HOST={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
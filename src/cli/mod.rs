@@ -5,6 +5,9 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Give up after waiting this many seconds for a lock held by another anvil
+    #[arg(long, global = true)]
+    pub lock_timeout: Option<u64>,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -13,14 +16,81 @@ pub enum Commands {
         url: String,
         #[arg(short = 'v', long)]
         version: Option<String>,
+        /// Always build from source, even if a prebuilt artefact is available
+        #[arg(long)]
+        build_from_source: bool,
+        /// Replace executables in ~/.anvil/bin that belong to something else
+        #[arg(long)]
+        force: bool,
     },
     Pack {
         v: String,
         #[arg(short = 't', long)]
         tag: bool,
+        /// Pack even with uncommitted changes, flagging the block as dirty
+        #[arg(long)]
+        allow_dirty: bool,
     },
     Switch {
         project: String,
         version: String,
+        /// Replace executables in ~/.anvil/bin that belong to something else
+        #[arg(long)]
+        force: bool,
+    },
+    Uninstall {
+        project: String,
+        /// Also remove the cloned repository
+        #[arg(long)]
+        purge: bool,
+    },
+    Update {
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        project: Option<String>,
+        /// Update every installed project
+        #[arg(long)]
+        all: bool,
+        /// Only report available updates, exit with an error if any
+        #[arg(long)]
+        check: bool,
+        /// Replace executables in ~/.anvil/bin that belong to something else
+        #[arg(long)]
+        force: bool,
+    },
+    Log {
+        /// Version range: `A..B`, `A..`, `..B` or a single version
+        range: Option<String>,
+        #[arg(long)]
+        oneline: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    Verify,
+    /// Rehash the chain with the current block hash encoding
+    Migrate,
+    /// Delete stored artefacts no reachable block refers to
+    Gc {
+        #[arg(long)]
+        dry_run: bool,
+        /// Only keep the last N blocks of each project chain
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        keep_last: Option<usize>,
+    },
+    /// Upload the artefacts of the chain missing from a remote store
+    Push {
+        /// Remote store, the first configured one by default
+        remote: Option<String>,
+    },
+    /// Download the artefacts of the chain missing from the local store
+    Pull {
+        /// Remote store, the first configured one by default
+        remote: Option<String>,
+    },
+    List {
+        /// Also show every version available in the cached repo
+        #[arg(short = 'a', long)]
+        all: bool,
+        #[arg(long)]
+        json: bool,
     },
 }
@@ -2,6 +2,8 @@ use std::{collections::HashMap, path::PathBuf};
 
 use serde::Deserialize;
 
+use crate::store::meta::ArtefactType;
+
 pub mod parse;
 
 #[derive(Debug, Deserialize, Default)]
@@ -11,6 +13,21 @@ pub struct Config {
     pub dependency_script: Option<String>,
     // pub dependencies: Vec<Dependency>,
     pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub compression: Compression,
+    /// Remote stores looked up, in order, when an artefact is not in the local store:
+    /// urls, or directories relative to the directory of this file
+    #[serde(default)]
+    pub stores: Vec<String>,
+}
+
+/// User level settings, read from `~/.anvil/config.yml`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct UserConfig {
+    /// Remote stores tried after the ones of the project, relative directories
+    /// being relative to `~/.anvil`
+    pub stores: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -21,10 +38,57 @@ pub struct Project {
 #[derive(Debug, Deserialize, Default)]
 pub struct Build {
     pub artifact_dir: PathBuf,
+    #[serde(default)]
     pub entrypoint: PathBuf,
     pub command: String,
     pub incremental: bool,
     pub jit: bool,
+    /// Outputs released together, packed instead of the single `entrypoint` when set
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+    /// Ship the whole `artifact_dir` as a `Pack` archive, `entrypoint` being inside it
+    #[serde(default)]
+    pub pack: bool,
+    /// Environment variables whose value is recorded in the provenance of each block
+    #[serde(default)]
+    pub env_whitelist: Vec<String>,
+}
+
+/// One build output, installed under `name`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub artefact_type: ArtefactType,
+    /// For `pack` artifacts, the executable inside `path` linked as `name`
+    #[serde(default)]
+    pub entrypoint: Option<PathBuf>,
+}
+
+/// How artefacts are compressed in the store, the artefact hash always covers
+/// the uncompressed bytes.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct Compression {
+    pub codec: Codec,
+    pub level: i32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Zstd,
+            level: 3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    None,
+    Zstd,
 }
 
 // #[derive(Debug, Deserialize)]
@@ -2,17 +2,45 @@ use std::{fs, path::Path};
 
 use anyhow::Result;
 
-use crate::config::Config;
+use crate::{
+    config::{Config, UserConfig},
+    core::cmd::anvil_home,
+    store::layered::is_url,
+};
 
 impl Config {
     pub fn new(path: Option<&Path>) -> Result<Self> {
-        let yaml = if let Some(p) = path {
-            println!("debug: config path: {}", p.display());
-            fs::read_to_string(p)?
-        } else {
-            fs::read_to_string(".anvil/anvil.yml")?
-        };
-        let config: Config = serde_yaml::from_str(&yaml)?;
+        let path = path.unwrap_or(Path::new(".anvil/anvil.yml"));
+        let mut config: Config = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+        resolve_stores(&mut config.stores, path)?;
         Ok(config)
     }
 }
+
+impl UserConfig {
+    /// The user config, or the defaults when there is none.
+    pub fn load() -> Result<Self> {
+        let path = anvil_home()?.join("config.yml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut config: UserConfig = serde_yaml::from_str(&fs::read_to_string(&path)?)?;
+        resolve_stores(&mut config.stores, &path)?;
+        Ok(config)
+    }
+}
+
+/// Make the relative store directories listed in the config file at `path`
+/// relative to the directory of that file.
+fn resolve_stores(stores: &mut [String], path: &Path) -> Result<()> {
+    let dir = std::path::absolute(path)?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for store in stores {
+        if !is_url(store) && Path::new(store).is_relative() {
+            *store = dir.join(&store).to_string_lossy().to_string();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use crate::{
+    core::{
+        AnvilCore,
+        cmd::install::{InstallMeta, load_block_from_repo},
+    },
+    store::{IndexEntry, meta::Meta, traits::Store},
+};
+
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub removed_entries: Vec<IndexEntry>,
+    pub removed_objects: Vec<(String, u64)>,
+}
+
+impl GcReport {
+    pub fn reclaimed(&self) -> u64 {
+        self.removed_objects.iter().map(|(_, size)| size).sum()
+    }
+}
+
+impl<S: Store> AnvilCore<S> {
+    pub fn gc(&self, project: &str, keep_last: Option<usize>, dry_run: bool) -> anyhow::Result<()> {
+        let report = self.collect_garbage(project, keep_last, dry_run)?;
+
+        let verb = if dry_run { "Would remove" } else { "Removed" };
+        for (hash, size) in &report.removed_objects {
+            println!("{verb} {hash} ({})", format_size(*size));
+        }
+        println!(
+            "{verb} {} object(s) and {} index entr{}, {} reclaimed",
+            report.removed_objects.len(),
+            report.removed_entries.len(),
+            if report.removed_entries.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            format_size(report.reclaimed())
+        );
+        Ok(())
+    }
+
+    /// Delete objects no reachable block refers to.
+    ///
+    /// Reachable blocks are the chain of `project`, the chains of installed projects
+    /// (their last `keep_last` blocks only, when set), and every installed version.
+    /// Index entries of projects whose chain is unknown here are all kept.
+    ///
+    /// The store stays locked from mark to sweep, so objects written by a concurrent
+    /// `pack` before it indexes them are not collected.
+    pub fn collect_garbage(
+        &self,
+        project: &str,
+        keep_last: Option<usize>,
+        dry_run: bool,
+    ) -> anyhow::Result<GcReport> {
+        let _lock = self.store.lock()?;
+
+        let mut known_projects = HashSet::new();
+        let mut keep_blocks = HashSet::new();
+        let mut keep_objects = HashSet::new();
+
+        let mut keep_chain = |name: &str, blocks: &[Meta]| {
+            known_projects.insert(name.to_string());
+            let kept = keep_last.unwrap_or(blocks.len());
+            for block in blocks.iter().rev().take(kept) {
+                keep_blocks.insert(block.block_hash.clone());
+                keep_objects.extend(block.object_hashes().into_iter().map(String::from));
+            }
+        };
+
+        if !self.blocks.is_empty() {
+            keep_chain(project, &self.blocks);
+        }
+
+        let mut pinned = Vec::new();
+        for (name, installed) in InstallMeta::all()? {
+            match load_block_from_repo(&installed.local_repo_path) {
+                Ok(blocks) => keep_chain(&name, &blocks),
+                Err(e) => eprintln!("warning: cannot read blocks of {name}, keeping all: {e}"),
+            }
+            pinned.extend(InstallMeta::installed_blocks(&name)?);
+            pinned.push(installed.current_block_hash);
+        }
+        keep_blocks.extend(pinned);
+
+        let mut report = GcReport::default();
+        for entry in self.store.index_entries()? {
+            if known_projects.contains(&entry.project) && !keep_blocks.contains(&entry.block_hash) {
+                report.removed_entries.push(entry);
+                continue;
+            }
+            // blocks outside the known chains may be manifests of other objects
+            if keep_objects.insert(entry.artefact_hash.clone())
+                && let Ok(Some(manifest)) = self.store.get_manifest(&entry.artefact_hash)
+            {
+                keep_objects.extend(manifest.artifacts.into_iter().map(|a| a.artefact_hash));
+            }
+        }
+
+        report.removed_objects = self
+            .store
+            .list_objects()?
+            .into_iter()
+            .filter(|(hash, _)| !keep_objects.contains(hash))
+            .collect();
+
+        if !dry_run {
+            for entry in &report.removed_entries {
+                self.store.remove_index_entry(entry)?;
+            }
+            for (hash, _) in &report.removed_objects {
+                self.store.remove_object(hash)?;
+            }
+        }
+        Ok(report)
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
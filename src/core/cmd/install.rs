@@ -1,34 +1,235 @@
 use std::{
-    env::home_dir,
+    os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     process::Stdio,
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     config::Config,
-    core::{AnvilCore, cmd::run_step},
-    store::{meta::Meta, traits::Store},
+    core::{
+        AnvilCore,
+        cmd::{anvil_home, replace_executable, replace_symlink, require_git, run_step},
+    },
+    store::{
+        archive::{pack_dir, unpack_dir},
+        atomic::{atomic_write, atomic_write_with_permissions},
+        meta::{ArtefactType, ManifestEntry, Meta},
+        traits::Store,
+    },
 };
 
-impl<S: Store> AnvilCore<S> {
-    pub fn install(&mut self, url: &str, version: Option<String>) -> anyhow::Result<()> {
-        let project_name = Self::extract_project_name(url)?;
-        let repo_path = self.repo_install_path(&project_name)?;
+/// Record of an installed project, stored in `~/.anvil/meta/<name>.json`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InstallMeta {
+    pub repo_url: String,
+    pub local_repo_path: PathBuf,
+    pub current_version: String,
+    pub current_commit: String,
+    #[serde(default)]
+    pub current_block_hash: String,
+    /// Head of the last remote chain that passed validation, used to detect rewritten history.
+    #[serde(default)]
+    pub trusted_head: Option<String>,
+    /// Objects fetched from a remote store into the local one by installs of this
+    /// project, released again by `uninstall`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cached_objects: Vec<String>,
+}
 
-        self.ensure_repo_cloned(url, &repo_path)?;
+impl InstallMeta {
+    pub fn path(name: &str) -> anyhow::Result<PathBuf> {
+        Ok(anvil_home()?.join("meta").join(format!("{name}.json")))
+    }
 
-        self.config = Config::new(Some(&repo_path.join(".anvil/anvil.yml")))?;
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let path = Self::path(name)?;
+        if !path.exists() {
+            anyhow::bail!("Project '{name}' is not installed");
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
 
-        let blocks = Self::load_block_from_repo(&repo_path)?;
-        let block = Self::resolve_version(&blocks, version)?;
-        let commit = block.git_commit.clone();
+    /// Every installed project, sorted by name.
+    pub fn all() -> anyhow::Result<Vec<(String, Self)>> {
+        let dir = anvil_home()?.join("meta");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut installed = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            installed.push((name.to_string(), Self::load(name)?));
+        }
+        installed.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(installed)
+    }
+
+    /// Directory holding every installed version of `name`.
+    pub fn versions_dir(name: &str) -> anyhow::Result<PathBuf> {
+        Ok(anvil_home()?.join("versions").join(name))
+    }
+
+    /// Block hashes of every version of `name` present in its versions directory.
+    pub fn installed_blocks(name: &str) -> anyhow::Result<Vec<String>> {
+        let dir = Self::versions_dir(name)?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut blocks = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                blocks.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(blocks)
+    }
+
+    /// Directory holding the artifacts of `name` installed for the block `block_hash`.
+    pub fn version_dir(name: &str, block_hash: &str) -> anyhow::Result<PathBuf> {
+        Ok(Self::versions_dir(name)?.join(block_hash))
+    }
+
+    /// Links in `~/.anvil/bin` pointing at an installed version of `name`.
+    pub fn links(name: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let bin_dir = anvil_home()?.join("bin");
+        if !bin_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let versions_dir = Self::versions_dir(name)?;
+
+        let mut links = Vec::new();
+        for entry in std::fs::read_dir(bin_dir)? {
+            let path = entry?.path();
+            if let Ok(target) = std::fs::read_link(&path)
+                && target.starts_with(&versions_dir)
+            {
+                links.push(path);
+            }
+        }
+        links.sort();
+        Ok(links)
+    }
+
+    /// Whether `block` is the installed version, possibly under a hash it had
+    /// before a migration, or by commit and version for installs recorded before
+    /// block hashes were.
+    pub fn is_current(&self, block: &Meta) -> bool {
+        if self.current_block_hash.is_empty() {
+            block.git_commit == self.current_commit && block.version == self.current_version
+        } else {
+            block.block_hash == self.current_block_hash
+                || block.migrated_from.contains(&self.current_block_hash)
+        }
+    }
+
+    pub fn save(&self, name: &str) -> anyhow::Result<()> {
+        let path = Self::path(name)?;
+        atomic_write(&path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+}
+
+pub fn extract_project_name(repo_url: &str) -> anyhow::Result<String> {
+    let name = repo_url
+        .rsplit('/')
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Invalid repo URL"))?
+        .replace(".git", "");
 
-        self.checkout_commit(&repo_path, &commit)?;
+    Ok(name)
+}
 
-        let bin_path = self.build_binary(&repo_path)?;
-        let final_bin = self.install_binary(&project_name, &bin_path)?;
+/// Load the chain from the fetched remote head, so versions packed after the
+/// currently checked out commit are still visible. Falls back to the worktree.
+pub fn load_block_from_repo(repo_path: &Path) -> anyhow::Result<Vec<Meta>> {
+    let output = std::process::Command::new("git")
+        .args(["-C", repo_path.to_str().unwrap()])
+        .args(["show", "origin/HEAD:.anvil/blocks.json"])
+        .stderr(Stdio::null())
+        .output()?;
 
-        self.update_meta(&project_name, url, &repo_path, block)?;
+    let content = if output.status.success() {
+        String::from_utf8(output.stdout)?
+    } else {
+        std::fs::read_to_string(repo_path.join(".anvil/blocks.json"))?
+    };
+    let blocks: Vec<Meta> = serde_json::from_str(&content)?;
+    Ok(blocks)
+}
+
+/// Refuse to replace `link` in `~/.anvil/bin` unless it is missing, a link of
+/// `name`, or the plain binary of `name` left by an anvil predating versioned installs.
+fn check_replaceable(name: &str, link: &Path) -> anyhow::Result<()> {
+    let Ok(metadata) = link.symlink_metadata() else {
+        return Ok(());
+    };
+    if metadata.is_symlink() {
+        let target = std::fs::read_link(link)?;
+        if target.starts_with(InstallMeta::versions_dir(name)?) {
+            return Ok(());
+        }
+        if let Ok(owned) = target.strip_prefix(anvil_home()?.join("versions"))
+            && let Some(owner) = owned.iter().next()
+        {
+            anyhow::bail!(
+                "{} is installed by {}, use --force to replace it",
+                link.display(),
+                owner.to_string_lossy()
+            );
+        }
+    } else if metadata.is_file()
+        && link.file_name() == Some(name.as_ref())
+        && InstallMeta::path(name)?.exists()
+    {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{} was not installed by anvil, use --force to replace it",
+        link.display()
+    )
+}
+
+impl<S: Store> AnvilCore<S> {
+    /// Install the project at `url`, at `version` or its latest block.
+    ///
+    /// `open_store` opens the store of the project once its repository is cloned,
+    /// so the stores the project configures are looked up as well.
+    pub fn install(
+        &mut self,
+        url: &str,
+        version: Option<String>,
+        build_from_source: bool,
+        force: bool,
+        open_store: impl FnOnce(&str) -> anyhow::Result<S>,
+    ) -> anyhow::Result<()> {
+        let project_name = extract_project_name(url)?;
+        let repo_path = self.repo_install_path(&project_name)?;
+
+        Self::ensure_repo_cloned(url, &repo_path)?;
+        self.store = open_store(&project_name)?;
+
+        let blocks = Self::load_trusted_blocks(&project_name, &repo_path)?;
+        let block = Self::resolve_version(&blocks, version)?;
+
+        let final_bin = self.install_block(
+            &project_name,
+            url,
+            &repo_path,
+            &blocks,
+            block,
+            build_from_source,
+            force,
+        )?;
 
         println!(
             "Installed {} ({}) at {}",
@@ -39,18 +240,175 @@ impl<S: Store> AnvilCore<S> {
         Ok(())
     }
 
-    fn extract_project_name(repo_url: &str) -> anyhow::Result<String> {
-        let name = repo_url
-            .rsplit('/')
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("Invalid repo URL"))?
-            .replace(".git", "");
+    /// Load the remote chain of `name`, refusing it if it is broken or if it no
+    /// longer contains the head trusted by a previous install.
+    pub(crate) fn load_trusted_blocks(name: &str, repo_path: &Path) -> anyhow::Result<Vec<Meta>> {
+        let blocks = load_block_from_repo(repo_path)?;
+        let trusted_head = if InstallMeta::path(name)?.exists() {
+            InstallMeta::load(name)?.trusted_head
+        } else {
+            None
+        };
+        Self::check_remote_chain(&blocks, trusted_head.as_deref())?;
+        Ok(blocks)
+    }
+
+    pub fn check_remote_chain(blocks: &[Meta], trusted_head: Option<&str>) -> anyhow::Result<()> {
+        let mut problems = Self::blocks_problems(blocks);
+        for (i, block) in blocks.iter().enumerate() {
+            if let Err(e) = block.check_artifacts() {
+                problems.push(format!("block {i} ({}): {e}", block.version));
+            }
+        }
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "Refusing to install from a broken chain:\n  {}",
+                problems.join("\n  ")
+            );
+        }
+
+        // a head rehashed by `anvil migrate` is still the same block
+        if let Some(head) = trusted_head
+            && !(0..blocks.len()).any(|i| Self::had_hash(blocks, i, head))
+        {
+            anyhow::bail!(
+                "Possible tampering: the remote chain no longer contains the previously trusted block {head}, its history was rewritten"
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether block `i` of the chain was hashed as `hash`: its current hash, or a
+    /// `migrated_from` entry that re-encoding the block as it was before that
+    /// migration reproduces, linked to a hash the previous block had at the time.
+    fn had_hash(blocks: &[Meta], i: usize, hash: &str) -> bool {
+        let block = &blocks[i];
+        if block.block_hash == hash {
+            return true;
+        }
+        let Some(k) = block.migrated_from.iter().position(|h| h == hash) else {
+            return false;
+        };
+        let prevs: Vec<Option<&String>> = match i.checked_sub(1) {
+            None => vec![None],
+            Some(p) => std::iter::once(&blocks[p].block_hash)
+                .chain(&blocks[p].migrated_from)
+                .map(Some)
+                .collect(),
+        };
+
+        // hash version 0 predates manifests and does not cover them
+        let versions = (0..=block.hash_version).filter(|&v| v > 0 || block.manifest.is_empty());
+        versions.into_iter().any(|hash_version| {
+            prevs.iter().any(|prev| {
+                let mut old = block.clone();
+                old.hash_version = hash_version;
+                old.prev_block_hash = prev.cloned();
+                old.migrated_from.truncate(k);
+                S::compute_block_hash(&old) == hash
+                    && prev.is_none_or(|prev| Self::had_hash(blocks, i - 1, prev))
+            })
+        })
+    }
+
+    /// Install `block`, activate it and record it in the meta file.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn install_block(
+        &mut self,
+        project_name: &str,
+        url: &str,
+        repo_path: &Path,
+        blocks: &[Meta],
+        block: &Meta,
+        build_from_source: bool,
+        force: bool,
+    ) -> anyhow::Result<PathBuf> {
+        let fetched =
+            self.ensure_version_installed(project_name, repo_path, block, build_from_source)?;
+        let final_bin = Self::activate(project_name, block, force)?;
+
+        self.update_meta(project_name, url, repo_path, blocks, block, fetched)?;
+        Ok(final_bin)
+    }
+
+    /// Make sure the version directory of `block` holds all its artifacts.
+    ///
+    /// An already installed version or verified artefacts from the store are used
+    /// when available, otherwise the block commit is checked out and built.
+    /// Returns the objects fetched from a remote store into the local one.
+    fn ensure_version_installed(
+        &mut self,
+        project_name: &str,
+        repo_path: &Path,
+        block: &Meta,
+        build_from_source: bool,
+    ) -> anyhow::Result<Vec<String>> {
+        let artifacts = block.artifacts(project_name);
+        let version_dir = InstallMeta::version_dir(project_name, &block.block_hash)?;
+        let mut fetched: Vec<String> = artifacts
+            .iter()
+            .map(|a| a.artefact_hash.clone())
+            .filter(|hash| !self.store.exists_locally(hash))
+            .collect();
+
+        if !build_from_source {
+            if artifacts.iter().all(|a| version_dir.join(&a.name).exists()) {
+                return Ok(Vec::new());
+            }
+            let stored: Option<Vec<Vec<u8>>> = artifacts
+                .iter()
+                .map(|a| self.stored_artifact(&a.artefact_hash))
+                .collect();
+            if let Some(stored) = stored {
+                println!("Using prebuilt artefact {}", block.artefact_hash);
+                for (artifact, bytes) in artifacts.iter().zip(stored) {
+                    self.install_bytes(project_name, &block.block_hash, artifact, &bytes)?;
+                }
+                return Ok(fetched);
+            }
+        }
+
+        if block.git_commit.is_empty() {
+            anyhow::bail!(
+                "Version {} of {project_name} was packed outside git, it can only be installed from a prebuilt artefact",
+                block.version
+            );
+        }
+        self.checkout_commit(repo_path, &block.git_commit)?;
+        self.config = Config::new(Some(&repo_path.join(".anvil/anvil.yml")))?;
+
+        self.build(repo_path)?;
+        for artifact in &artifacts {
+            let path = repo_path.join(&artifact.path);
+            let bytes = if artifact.artefact_type == ArtefactType::Pack {
+                pack_dir(&path)?
+            } else {
+                std::fs::read(path)?
+            };
+            self.install_bytes(project_name, &block.block_hash, artifact, &bytes)?;
+        }
 
-        Ok(name)
+        // artifacts fetched before another one turned out to be missing stay cached
+        fetched.retain(|hash| self.store.exists_locally(hash));
+        Ok(fetched)
+    }
+
+    /// Artifact bytes from the store, if present and matching `artefact_hash`.
+    fn stored_artifact(&self, artefact_hash: &str) -> Option<Vec<u8>> {
+        if !self.store.exists(artefact_hash) {
+            return None;
+        }
+        match self.store.get_artifact(artefact_hash) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                println!("warning: {e}, building from source");
+                None
+            }
+        }
     }
 
     fn repo_install_path(&self, name: &str) -> anyhow::Result<PathBuf> {
-        let path = home_dir().unwrap().join(".anvil/repo"); //TODO: remove unwrap()
+        let path = anvil_home()?.join("repo");
 
         if !path.exists() {
             std::fs::create_dir_all(&path)?;
@@ -59,7 +417,8 @@ impl<S: Store> AnvilCore<S> {
         Ok(path.join(name))
     }
 
-    fn ensure_repo_cloned(&self, url: &str, path: &Path) -> anyhow::Result<()> {
+    pub(crate) fn ensure_repo_cloned(url: &str, path: &Path) -> anyhow::Result<()> {
+        require_git("install from a repository")?;
         if path.exists() {
             // update
             // std::process::Command::new("git")
@@ -93,14 +452,10 @@ impl<S: Store> AnvilCore<S> {
         Ok(())
     }
 
-    fn load_block_from_repo(repo_path: &Path) -> anyhow::Result<Vec<Meta>> {
-        let block_path = repo_path.join(".anvil/blocks.json");
-        let content = std::fs::read_to_string(block_path)?;
-        let blocks: Vec<Meta> = serde_json::from_str(&content)?;
-        Ok(blocks)
-    }
-
-    fn resolve_version(blocks: &[Meta], version: Option<String>) -> anyhow::Result<&Meta> {
+    pub(crate) fn resolve_version(
+        blocks: &[Meta],
+        version: Option<String>,
+    ) -> anyhow::Result<&Meta> {
         if let Some(v) = version {
             blocks
                 .iter()
@@ -114,15 +469,22 @@ impl<S: Store> AnvilCore<S> {
     }
 
     pub fn checkout_commit(&self, repo_path: &Path, commit: &str) -> anyhow::Result<()> {
-        std::process::Command::new("git")
+        require_git("build from source")?;
+        let status = std::process::Command::new("git")
             .args(["-C", repo_path.to_str().unwrap(), "checkout", commit])
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .status()?;
+        if !status.success() {
+            anyhow::bail!(
+                "Cannot check out commit {commit} in {}",
+                repo_path.display()
+            );
+        }
         Ok(())
     }
 
-    pub fn build_binary(&self, repo_path: &Path) -> anyhow::Result<PathBuf> {
+    pub fn build(&self, repo_path: &Path) -> anyhow::Result<()> {
         // run_build_cmd(&self.config.build, repo_path)?;
         run_step(
             &self.config.build.command,
@@ -130,42 +492,105 @@ impl<S: Store> AnvilCore<S> {
             "cyan",
             "Forging ...",
             "Package forged !",
-        )?;
+        )
+    }
 
-        Ok(repo_path.join(&self.config.build.entrypoint))
+    /// Write one artifact into its version directory, without activating it.
+    pub(crate) fn install_bytes(
+        &self,
+        name: &str,
+        block_hash: &str,
+        artifact: &ManifestEntry,
+        bytes: &[u8],
+    ) -> anyhow::Result<PathBuf> {
+        let install_path = InstallMeta::version_dir(name, block_hash)?.join(&artifact.name);
+        match artifact.artefact_type {
+            ArtefactType::Pack => unpack_dir(bytes, &install_path)?,
+            ArtefactType::Lib => {
+                let mode = std::fs::Permissions::from_mode(0o644);
+                atomic_write_with_permissions(&install_path, bytes, Some(mode))?;
+            }
+            _ => replace_executable(&install_path, bytes)?,
+        }
+        Ok(install_path)
     }
 
-    fn install_binary(&self, name: &str, compiled_bin: &PathBuf) -> anyhow::Result<PathBuf> {
-        let install_path = home_dir().unwrap().join(".anvil/bin").join(name);
+    /// Point `~/.anvil/bin/<binary>` at the installed version of `block`, for each
+    /// binary of the block, and drop links to binaries the block no longer ships.
+    ///
+    /// Links of other projects and files anvil did not install are only replaced
+    /// when `force` is set.
+    ///
+    /// Returns the link of the main binary, or the version directory when the block
+    /// has no binary.
+    pub(crate) fn activate(name: &str, block: &Meta, force: bool) -> anyhow::Result<PathBuf> {
+        let version_dir = InstallMeta::version_dir(name, &block.block_hash)?;
+        let artifacts = block.artifacts(name);
+        if !artifacts.iter().all(|a| version_dir.join(&a.name).exists()) {
+            anyhow::bail!("Version {} of {name} is not installed", block.version);
+        }
 
-        std::fs::create_dir_all(install_path.parent().unwrap())?;
-        std::fs::copy(compiled_bin, &install_path)?;
+        let bin_dir = anvil_home()?.join("bin");
+        let mut links = Vec::new();
+        for artifact in &artifacts {
+            let target = match (&artifact.artefact_type, &artifact.entrypoint) {
+                (ArtefactType::Lib, _) | (ArtefactType::Pack, None) => continue,
+                (ArtefactType::Pack, Some(entrypoint)) => {
+                    version_dir.join(&artifact.name).join(entrypoint)
+                }
+                _ => version_dir.join(&artifact.name),
+            };
+            let link = bin_dir.join(&artifact.name);
+            if !force {
+                check_replaceable(name, &link)?;
+            }
+            replace_symlink(&target, &link)?;
+            links.push(link);
+        }
 
-        Ok(install_path)
+        for stale in InstallMeta::links(name)? {
+            if !links.contains(&stale) {
+                std::fs::remove_file(stale)?;
+            }
+        }
+
+        let main = bin_dir.join(name);
+        Ok(if links.contains(&main) {
+            main
+        } else {
+            links.into_iter().next().unwrap_or(version_dir)
+        })
     }
 
-    fn update_meta(
+    pub(crate) fn update_meta(
         &self,
         name: &str,
         url: &str,
-        repo_path: &PathBuf,
+        repo_path: &Path,
+        blocks: &[Meta],
         block: &Meta,
+        fetched: Vec<String>,
     ) -> anyhow::Result<()> {
-        let meta_path = home_dir()
-            .unwrap()
-            .join(".anvil/meta")
-            .join(format!("{name}.json"));
-
-        std::fs::create_dir_all(meta_path.parent().unwrap())?;
-
-        let meta = serde_json::json!({
-            "repo_url": url,
-            "local_repo_path": repo_path,
-            "current_version": block.version,
-            "current_commit": block.git_commit,
-        });
+        let mut cached_objects = if InstallMeta::path(name)?.exists() {
+            InstallMeta::load(name)?.cached_objects
+        } else {
+            Vec::new()
+        };
+        for hash in fetched {
+            if !cached_objects.contains(&hash) {
+                cached_objects.push(hash);
+            }
+        }
 
-        std::fs::write(meta_path, serde_json::to_string_pretty(&meta)?)?;
-        Ok(())
+        InstallMeta {
+            repo_url: url.to_string(),
+            local_repo_path: repo_path.to_path_buf(),
+            current_version: block.version.clone(),
+            current_commit: block.git_commit.clone(),
+            current_block_hash: block.block_hash.clone(),
+            trusted_head: blocks.last().map(|b| b.block_hash.clone()),
+            cached_objects,
+        }
+        .save(name)
     }
 }
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::core::cmd::{
+    anvil_home,
+    install::{InstallMeta, load_block_from_repo},
+    short,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    pub name: String,
+    pub repo_url: String,
+    pub current_version: String,
+    pub current_commit: String,
+    pub install_path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<Vec<VersionEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionEntry {
+    pub version: String,
+    pub block_hash: String,
+    pub git_commit: String,
+    pub active: bool,
+}
+
+pub fn list(all: bool, json: bool) -> anyhow::Result<()> {
+    let bin_dir = anvil_home()?.join("bin");
+
+    let mut entries = Vec::new();
+    for (name, installed) in InstallMeta::all()? {
+        let versions = if all {
+            Some(available_versions(&installed))
+        } else {
+            None
+        };
+        entries.push(ListEntry {
+            install_path: bin_dir.join(&name),
+            name,
+            repo_url: installed.repo_url,
+            current_version: installed.current_version,
+            current_commit: installed.current_commit,
+            versions,
+        });
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else if entries.is_empty() {
+        println!("No project installed");
+    } else {
+        print!("{}", render_table(&entries));
+    }
+    Ok(())
+}
+
+fn available_versions(installed: &InstallMeta) -> Vec<VersionEntry> {
+    let blocks = match load_block_from_repo(&installed.local_repo_path) {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            eprintln!(
+                "warning: cannot read blocks of {}: {e}",
+                installed.local_repo_path.display()
+            );
+            return Vec::new();
+        }
+    };
+
+    blocks
+        .into_iter()
+        .map(|b| VersionEntry {
+            active: installed.is_current(&b),
+            version: b.version,
+            block_hash: b.block_hash,
+            git_commit: b.git_commit,
+        })
+        .collect()
+}
+
+pub fn render_table(entries: &[ListEntry]) -> String {
+    let header = ["NAME", "VERSION", "COMMIT", "REPO", "PATH"];
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|e| {
+            [
+                e.name.clone(),
+                e.current_version.clone(),
+                short(&e.current_commit).to_string(),
+                e.repo_url.clone(),
+                e.install_path.display().to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+
+    let format_row = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(c, w)| format!("{c:<w$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut out = format_row(&header);
+    for (entry, row) in entries.iter().zip(&rows) {
+        out.push_str(&format_row(&row.each_ref().map(String::as_str)));
+        for v in entry.versions.iter().flatten() {
+            let marker = if v.active { '*' } else { ' ' };
+            out.push_str(&format!(
+                "  {marker} {:<10} {} ({})\n",
+                v.version,
+                short(&v.block_hash),
+                short(&v.git_commit)
+            ));
+        }
+    }
+    out
+}
@@ -0,0 +1,129 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    core::{AnvilCore, cmd::short},
+    store::{meta::Meta, traits::Store},
+};
+
+impl<S: Store> AnvilCore<S> {
+    pub fn log(&self, oneline: bool, json: bool, range: Option<&str>) -> anyhow::Result<()> {
+        let blocks = select_range(&self.blocks, range)?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&blocks)?);
+        } else if blocks.is_empty() {
+            println!("No blocks packed yet");
+        } else {
+            print!("{}", render_log(&blocks, oneline));
+        }
+        Ok(())
+    }
+}
+
+/// Blocks matching `range` (`A..B`, `A..`, `..B` or a single version), newest first.
+pub fn select_range<'a>(blocks: &'a [Meta], range: Option<&str>) -> anyhow::Result<Vec<&'a Meta>> {
+    let position = |v: &str| {
+        blocks
+            .iter()
+            .position(|b| b.version == v)
+            .ok_or_else(|| anyhow::anyhow!("Version {v} not found"))
+    };
+
+    let Some(r) = range else {
+        return Ok(blocks.iter().rev().collect());
+    };
+    let (start, end) = match r.split_once("..") {
+        Some((from, to)) => {
+            let start = if from.is_empty() { 0 } else { position(from)? };
+            let end = if to.is_empty() {
+                blocks.len()
+            } else {
+                position(to)? + 1
+            };
+            (start, end)
+        }
+        None => {
+            let i = position(r)?;
+            (i, i + 1)
+        }
+    };
+
+    if start >= end {
+        anyhow::bail!("Empty range: {r}");
+    }
+    Ok(blocks[start..end].iter().rev().collect())
+}
+
+pub fn render_log(blocks: &[&Meta], oneline: bool) -> String {
+    let mut out = String::new();
+    for b in blocks {
+        if oneline {
+            out.push_str(&format!(
+                "{} {} {} {}\n",
+                short(&b.block_hash),
+                b.version,
+                short(&b.git_commit),
+                &format_date(b.created_at)[..10]
+            ));
+            continue;
+        }
+        let prev = b.prev_block_hash.as_deref().map(short).unwrap_or("genesis");
+        out.push_str(&format!("block {} ({})\n", b.block_hash, b.version));
+        out.push_str(&format!("Prev:     {prev}\n"));
+        out.push_str(&format!("Commit:   {}\n", b.git_commit));
+        out.push_str(&format!(
+            "Artefact: {} ({:?})\n",
+            b.artefact_hash, b.artefact_type
+        ));
+        let p = &b.provenance;
+        if let Some(tree) = &p.git_tree_hash {
+            let dirty = if p.dirty == Some(true) {
+                " (dirty)"
+            } else {
+                ""
+            };
+            out.push_str(&format!("Tree:     {tree}{dirty}\n"));
+        }
+        if let Some(command) = &p.build_command {
+            out.push_str(&format!("Build:    {command}\n"));
+        }
+        if let Some(config_hash) = &p.config_hash {
+            out.push_str(&format!("Config:   {config_hash}\n"));
+        }
+        for (name, value) in &p.env {
+            out.push_str(&format!("Env:      {name}={value}\n"));
+        }
+        if let (Some(host), Some(anvil)) = (&p.host, &p.anvil_version) {
+            out.push_str(&format!("Host:     {host}, anvil {anvil}\n"));
+        }
+        out.push_str(&format!("Date:     {}\n\n", format_date(b.created_at)));
+    }
+    out
+}
+
+/// Render a timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // civil_from_days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
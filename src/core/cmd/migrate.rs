@@ -0,0 +1,55 @@
+use crate::{
+    core::AnvilCore,
+    store::{meta::HASH_VERSION, traits::Store},
+};
+
+impl<S: Store> AnvilCore<S> {
+    /// Rehash every block with [`HASH_VERSION`], relinking the chain and moving
+    /// the store index to the new block hashes. Returns the number of migrated blocks.
+    ///
+    /// Rehashed blocks remember their previous hash in `migrated_from`.
+    pub fn migrate_chain(&mut self) -> anyhow::Result<usize> {
+        let _lock = self.lock_chain()?;
+        if self.blocks.iter().all(|b| b.hash_version == HASH_VERSION) {
+            return Ok(0);
+        }
+
+        self.validate_chain()
+            .map_err(|e| anyhow::anyhow!("refusing to migrate a broken chain: {e}"))?;
+
+        let mut migrated = 0;
+        let mut prev_block_hash = None;
+        for block in self.blocks.iter_mut() {
+            let old_hash = block.block_hash.clone();
+            if block.hash_version != HASH_VERSION {
+                block.hash_version = HASH_VERSION;
+                migrated += 1;
+            }
+            block.prev_block_hash = prev_block_hash;
+            if S::compute_block_hash(block) != old_hash {
+                block.migrated_from.push(old_hash.clone());
+                block.block_hash = S::compute_block_hash(block);
+            }
+
+            if old_hash != block.block_hash
+                && let Some(artefact_hash) = self.store.block_artifact(&old_hash)?
+            {
+                let bytes = self.store.get_artifact(&artefact_hash)?;
+                self.store.add_artifact(&bytes, block)?;
+                self.store.remove_block(&old_hash)?;
+            }
+            prev_block_hash = Some(block.block_hash.clone());
+        }
+
+        self.save_blocks()?;
+        Ok(migrated)
+    }
+
+    pub fn migrate(&mut self) -> anyhow::Result<()> {
+        match self.migrate_chain()? {
+            0 => println!("Chain already uses hash version {HASH_VERSION}"),
+            n => println!("Migrated {n} block(s) to hash version {HASH_VERSION}"),
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use std::{
+    env::home_dir,
+    os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     process::Stdio,
     time::Duration,
@@ -6,10 +8,83 @@ use std::{
 
 use indicatif::{ProgressBar, ProgressStyle};
 
-use crate::config::Build;
+use crate::{config::Build, store::atomic::atomic_write_with_permissions};
 
+pub mod gc;
 pub mod install;
+pub mod list;
+pub mod log;
+pub mod migrate;
 pub mod pack;
+pub mod pull;
+pub mod push;
+pub mod switch;
+pub mod uninstall;
+pub mod update;
+pub mod verify;
+
+/// Root of the user level anvil directory (`~/.anvil`).
+pub fn anvil_home() -> anyhow::Result<PathBuf> {
+    home_dir()
+        .map(|h| h.join(".anvil"))
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine home directory"))
+}
+
+/// First 8 characters of a hash, enough to tell blocks and commits apart on screen.
+pub fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(8)]
+}
+
+/// Replace `dest` with an executable containing `bytes`.
+///
+/// The bytes are written to a temporary file in the same directory and renamed
+/// over `dest`, so a running shell never sees a half-written binary.
+pub fn replace_executable(dest: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    atomic_write_with_permissions(dest, bytes, Some(std::fs::Permissions::from_mode(0o755)))
+}
+
+/// Point the symlink `link` at `target`, replacing whatever `link` was.
+///
+/// The new link is created under a temporary name and renamed into place.
+pub fn replace_symlink(target: &Path, link: &Path) -> anyhow::Result<()> {
+    let dir = link
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid link path: {}", link.display()))?;
+    std::fs::create_dir_all(dir)?;
+
+    let tmp = tempfile::Builder::new()
+        .prefix(".anvil-link")
+        .make_in(dir, |p| std::os::unix::fs::symlink(target, p))?;
+    tmp.persist(link)?;
+    Ok(())
+}
+
+/// Progress bar counting the artefacts of a push or pull.
+pub fn transfer_bar(len: usize, color: &str) -> ProgressBar {
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(&format!(
+                "{{spinner:.{color}}} [{{bar:30.{color}}}] {{pos}}/{{len}} {{msg}}"
+            ))
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    pb
+}
+
+/// Fail with a clear error when git, needed to `action`, cannot be run.
+pub fn require_git(action: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new("git")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        _ => anyhow::bail!("git is required to {action} but could not be run, is it installed?"),
+    }
+}
 
 pub fn run_build_cmd(build: &Build, project_root: &PathBuf) -> anyhow::Result<()> {
     let pb = ProgressBar::new_spinner();
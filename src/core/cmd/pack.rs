@@ -1,15 +1,69 @@
-use std::time::SystemTime;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use crate::{
-    core::{AnvilCore, cmd::run_step},
+    config::Artifact,
+    core::{
+        AnvilCore,
+        cmd::{require_git, run_step},
+    },
     store::{
-        meta::{ArtefactType, Meta},
+        archive::{pack_dir, tree_hash},
+        meta::{
+            ArtefactType, HASH_VERSION, Manifest, ManifestEntry, Meta, Provenance, get_tree_hash,
+            is_git_repo, worktree_changes,
+        },
         traits::Store,
     },
 };
 
+/// Block artefact bytes, manifest entries and `(hash, bytes)` of every artifact.
+type ArtifactSet = (Vec<u8>, Vec<ManifestEntry>, Vec<(String, Vec<u8>)>);
+
 impl<S: Store> AnvilCore<S> {
-    pub fn pack(&mut self, v: &str, tag: bool) -> anyhow::Result<()> {
+    pub fn pack(&mut self, v: &str, tag: bool, allow_dirty: bool) -> anyhow::Result<()> {
+        let _lock = self.lock_chain()?;
+
+        // outside git the sources are identified by their content instead of a commit
+        let source_hash = if self.current_commit.is_some() {
+            None
+        } else {
+            // without git a checkout would be packed without any commit to rebuild from
+            if self
+                .project_root
+                .ancestors()
+                .any(|d| d.join(".git").exists())
+            {
+                require_git("pack a git repository")?;
+            }
+            if is_git_repo(&self.project_root) {
+                anyhow::bail!(
+                    "The git repository has no commit yet, commit the sources before packing"
+                );
+            }
+            if tag {
+                anyhow::bail!("Cannot create tag: the project is not a git repository");
+            }
+            Some(self.source_hash()?)
+        };
+
+        // the recorded commit must be what gets built, or installs rebuilding it differ
+        let changes = worktree_changes(&self.project_root);
+        if let Some(changes) = &changes
+            && !changes.is_empty()
+        {
+            if !allow_dirty {
+                anyhow::bail!(
+                    "Refusing to pack from a dirty worktree, commit or stash these changes or pass --allow-dirty:\n  {}",
+                    changes.join("\n  ")
+                );
+            }
+            println!("warning: packing from a dirty worktree, the block is flagged as dirty");
+        }
+
         if let Some(script) = &self.config.dependency_script {
             let status = std::process::Command::new("sh")
                 .arg(script)
@@ -30,8 +84,7 @@ impl<S: Store> AnvilCore<S> {
 
         // run_build_cmd(&self.config.build, &self.project_root)?;
 
-        let entrypoint_path = self.project_root.join(&self.config.build.entrypoint);
-        let artifact_bytes = std::fs::read(&entrypoint_path)?;
+        let (artifact_bytes, manifest, objects) = self.read_artifacts()?;
         let artefact_hash = S::compute_hash(&artifact_bytes);
 
         if let Some(existing_block) = self
@@ -46,20 +99,45 @@ impl<S: Store> AnvilCore<S> {
             return Ok(());
         }
 
+        let artefact_type = if manifest.is_empty() {
+            ArtefactType::Bin
+        } else {
+            ArtefactType::Manifest
+        };
+        let git_commit = self.current_commit.clone().unwrap_or_default();
         let mut meta = Meta {
             artefact_hash,
-            artefact_type: ArtefactType::Bin,
+            artefact_type,
             created_at: SystemTime::now(),
-            git_commit: self.current_commit.clone().unwrap(),
+            git_commit: git_commit.clone(),
             prev_block_hash: self.blocks.last().map(|b| b.block_hash.clone()),
             block_hash: String::new(),
-            entrypoint: self.config.build.entrypoint.to_string_lossy().to_string(),
+            hash_version: HASH_VERSION,
+            entrypoint: self
+                .relative(&self.config.build.entrypoint)
+                .to_string_lossy()
+                .to_string(),
             version: v.to_string(),
+            manifest,
+            migrated_from: Vec::new(),
+            provenance: self.provenance(
+                &git_commit,
+                changes.map(|c| !c.is_empty()),
+                source_hash,
+            )?,
         };
 
+        meta.check_artifacts()?;
         meta.block_hash = S::compute_block_hash(&meta);
 
+        // the block is only recorded once its artefacts are durably stored, and
+        // the store lock keeps a gc from collecting them before that
+        let store_lock = self.store.lock()?;
+        for (hash, bytes) in &objects {
+            self.store.put_object(hash, bytes)?;
+        }
         self.store.add_artifact(&artifact_bytes, &meta)?;
+        drop(store_lock);
         self.blocks.push(meta);
         self.save_blocks()?;
 
@@ -77,7 +155,155 @@ impl<S: Store> AnvilCore<S> {
         Ok(())
     }
 
+    /// The artefact of the block with its manifest, and the objects of every artifact.
+    ///
+    /// Without `build.artifacts` nor `build.pack` the artefact is the entrypoint
+    /// itself, otherwise it is the manifest of the declared artifacts.
+    fn read_artifacts(&self) -> anyhow::Result<ArtifactSet> {
+        let build = &self.config.build;
+        let artifacts = if build.pack {
+            if !build.artifacts.is_empty() {
+                anyhow::bail!("build.pack and build.artifacts cannot be used together");
+            }
+            vec![self.artifact_dir_pack()?]
+        } else {
+            build.artifacts.clone()
+        };
+
+        if artifacts.is_empty() {
+            let entrypoint_path = self.project_root.join(&build.entrypoint);
+            return Ok((std::fs::read(&entrypoint_path)?, Vec::new(), Vec::new()));
+        }
+
+        let mut manifest = Vec::new();
+        let mut objects = Vec::new();
+        for artifact in &artifacts {
+            let path = self.project_root.join(&artifact.path);
+            let bytes = if artifact.artefact_type == ArtefactType::Pack {
+                pack_dir(&path)?
+            } else {
+                std::fs::read(&path)
+                    .map_err(|e| anyhow::anyhow!("Cannot read artifact {}: {e}", path.display()))?
+            };
+            let artefact_hash = S::compute_hash(&bytes);
+
+            manifest.push(ManifestEntry {
+                name: artifact.name.clone(),
+                path: self.relative(&artifact.path).to_string_lossy().to_string(),
+                artefact_type: artifact.artefact_type.clone(),
+                artefact_hash: artefact_hash.clone(),
+                entrypoint: artifact
+                    .entrypoint
+                    .as_ref()
+                    .map(|e| e.to_string_lossy().to_string()),
+            });
+            objects.push((artefact_hash, bytes));
+        }
+        Ok((Manifest::new(&manifest).to_bytes(), manifest, objects))
+    }
+
+    /// Everything needed to tell how the artefact packed from `git_commit` was produced.
+    fn provenance(
+        &self,
+        git_commit: &str,
+        dirty: Option<bool>,
+        source_hash: Option<String>,
+    ) -> anyhow::Result<Provenance> {
+        let config_path = self.project_root.join(".anvil/anvil.yml");
+        let config_hash = if config_path.exists() {
+            Some(S::compute_hash(&std::fs::read(config_path)?))
+        } else {
+            None
+        };
+
+        let mut env = BTreeMap::new();
+        for name in &self.config.build.env_whitelist {
+            if name.is_empty() || name.contains([':', '=', '\n']) {
+                anyhow::bail!("Invalid environment variable name in build.env_whitelist: {name:?}");
+            }
+            if let Ok(value) = std::env::var(name) {
+                env.insert(name.clone(), value);
+            }
+        }
+
+        let git_tree_hash = if git_commit.is_empty() {
+            None
+        } else {
+            get_tree_hash(&self.project_root, git_commit)
+        };
+
+        Ok(Provenance {
+            git_tree_hash,
+            dirty,
+            source_hash,
+            build_command: Some(self.config.build.command.clone()),
+            config_hash,
+            env,
+            host: Some(env!("ANVIL_HOST").to_string()),
+            anvil_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        })
+    }
+
+    /// `path` relative to the project root when it is inside it, as recorded in blocks.
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.project_root).unwrap_or(path)
+    }
+
+    /// Content hash of the project sources, leaving out anvil files and the build
+    /// outputs, which are not sources.
+    fn source_hash(&self) -> anyhow::Result<String> {
+        let build = &self.config.build;
+        let outputs = [&build.artifact_dir, &build.entrypoint]
+            .into_iter()
+            .chain(build.artifacts.iter().map(|a| &a.path));
+
+        let mut exclude = vec![PathBuf::from(".git"), PathBuf::from(".anvil")];
+        for path in outputs {
+            let path = self.relative(path);
+            if !path.as_os_str().is_empty() {
+                exclude.push(path.to_path_buf());
+            }
+        }
+        tree_hash(&self.project_root, &exclude)
+    }
+
+    /// `artifact_dir` as a single `Pack` artifact named after the project, linking
+    /// the entrypoint when there is one.
+    fn artifact_dir_pack(&self) -> anyhow::Result<Artifact> {
+        let build = &self.config.build;
+        let entrypoint = if build.entrypoint.as_os_str().is_empty() {
+            None
+        } else {
+            let inside = build
+                .entrypoint
+                .strip_prefix(&build.artifact_dir)
+                .map_err(|_| {
+                    anyhow::anyhow!("build.entrypoint must be inside build.artifact_dir")
+                })?;
+            Some(inside.to_path_buf())
+        };
+
+        let name = if self.config.project.name.is_empty() {
+            build
+                .entrypoint
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .ok_or_else(|| anyhow::anyhow!("project.name is required to pack artifact_dir"))?
+        } else {
+            self.config.project.name.clone()
+        };
+
+        Ok(Artifact {
+            path: build.artifact_dir.clone(),
+            name,
+            artefact_type: ArtefactType::Pack,
+            entrypoint,
+        })
+    }
+
     fn create_git_tag(&self, version: &str) -> anyhow::Result<()> {
+        require_git("create a tag")?;
+
         let status = std::process::Command::new("git")
             .arg("rev-parse")
             .arg("--is-inside-work-tree")
@@ -0,0 +1,63 @@
+use crate::{
+    core::{
+        AnvilCore,
+        cmd::{push::TransferReport, transfer_bar},
+    },
+    store::traits::Store,
+};
+
+impl<S: Store> AnvilCore<S> {
+    pub fn pull<R: Store + ?Sized>(&self, remote: &R) -> anyhow::Result<()> {
+        let report = self.pull_from(remote)?;
+        println!(
+            "Pulled {} artefact(s) from {}, {} already present",
+            report.transferred.len(),
+            remote.root().display(),
+            report.skipped
+        );
+        Ok(())
+    }
+
+    /// Download the artefact of every block of the chain missing from the local store.
+    ///
+    /// Downloads are checked against the artefact hash before being stored, and
+    /// blocks already present are skipped, so an interrupted pull can be resumed.
+    pub fn pull_from<R: Store + ?Sized>(&self, remote: &R) -> anyhow::Result<TransferReport> {
+        self.validate_chain()?;
+
+        let mut report = TransferReport::default();
+        let pb = transfer_bar(self.blocks.len(), "green");
+
+        for block in &self.blocks {
+            pb.set_message(block.version.clone());
+
+            let indexed = self.store.block_artifact(&block.block_hash)?;
+            if indexed.as_deref() == Some(block.artefact_hash.as_str())
+                && block.object_hashes().iter().all(|h| self.store.exists(h))
+            {
+                report.skipped += 1;
+                pb.inc(1);
+                continue;
+            }
+
+            let _lock = self.store.lock()?;
+            for artifact in &block.manifest {
+                if !self.store.exists(&artifact.artefact_hash) {
+                    let bytes = remote.get_artifact(&artifact.artefact_hash).map_err(|e| {
+                        anyhow::anyhow!("Cannot pull {} of {}: {e}", artifact.name, block.version)
+                    })?;
+                    self.store.put_object(&artifact.artefact_hash, &bytes)?;
+                }
+            }
+            let bytes = remote
+                .get_artifact(&block.artefact_hash)
+                .map_err(|e| anyhow::anyhow!("Cannot pull {}: {e}", block.version))?;
+            self.store.add_artifact(&bytes, block)?;
+            report.transferred.push(block.version.clone());
+            pb.inc(1);
+        }
+
+        pb.finish_and_clear();
+        Ok(report)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    core::{AnvilCore, cmd::transfer_bar},
+    store::traits::Store,
+};
+
+/// Outcome of a push or pull: versions transferred and blocks already present.
+#[derive(Debug, Default)]
+pub struct TransferReport {
+    pub transferred: Vec<String>,
+    pub skipped: usize,
+}
+
+impl<S: Store> AnvilCore<S> {
+    pub fn push<R: Store + ?Sized>(&self, remote: &R) -> anyhow::Result<()> {
+        let report = self.push_to(remote)?;
+        println!(
+            "Pushed {} artefact(s) to {}, {} already present",
+            report.transferred.len(),
+            remote.root().display(),
+            report.skipped
+        );
+        Ok(())
+    }
+
+    /// Upload the artefact of every block of the chain the remote does not have yet.
+    ///
+    /// Each artefact is stored on its own, so an interrupted push resumes where
+    /// it stopped when run again.
+    pub fn push_to<R: Store + ?Sized>(&self, remote: &R) -> anyhow::Result<TransferReport> {
+        self.validate_chain()?;
+
+        let mut report = TransferReport::default();
+        let pb = transfer_bar(self.blocks.len(), "cyan");
+
+        for block in &self.blocks {
+            pb.set_message(block.version.clone());
+
+            let indexed = remote.block_artifact(&block.block_hash)?;
+            if indexed.as_deref() == Some(block.artefact_hash.as_str())
+                && block.object_hashes().iter().all(|h| remote.exists(h))
+            {
+                report.skipped += 1;
+                pb.inc(1);
+                continue;
+            }
+
+            // artifacts go first, the block is only indexed once they are all there
+            let _lock = remote.lock()?;
+            for artifact in &block.manifest {
+                if !remote.exists(&artifact.artefact_hash) {
+                    let bytes = self
+                        .store
+                        .get_artifact(&artifact.artefact_hash)
+                        .map_err(|e| {
+                            anyhow::anyhow!(
+                                "Cannot push {} of {}: {e}",
+                                artifact.name,
+                                block.version
+                            )
+                        })?;
+                    remote.put_object(&artifact.artefact_hash, &bytes)?;
+                }
+            }
+            let bytes = self
+                .store
+                .get_artifact(&block.artefact_hash)
+                .map_err(|e| anyhow::anyhow!("Cannot push {}: {e}", block.version))?;
+            remote.add_artifact(&bytes, block)?;
+            report.transferred.push(block.version.clone());
+            pb.inc(1);
+        }
+
+        pb.finish_and_clear();
+        Ok(report)
+    }
+}
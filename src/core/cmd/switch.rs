@@ -0,0 +1,38 @@
+use crate::{
+    core::{AnvilCore, cmd::install::InstallMeta},
+    store::traits::Store,
+};
+
+impl<S: Store> AnvilCore<S> {
+    pub fn switch(&mut self, project: &str, version: &str, force: bool) -> anyhow::Result<()> {
+        let installed = InstallMeta::load(project)?;
+        let repo_path = installed.local_repo_path.clone();
+
+        let blocks = Self::load_trusted_blocks(project, &repo_path)?;
+        let block = Self::resolve_version(&blocks, Some(version.to_string()))?;
+
+        if installed.is_current(block) {
+            println!("{project} is already at {version}");
+            return Ok(());
+        }
+
+        let final_bin = self.install_block(
+            project,
+            &installed.repo_url,
+            &repo_path,
+            &blocks,
+            block,
+            false,
+            force,
+        )?;
+
+        println!(
+            "Switched {} from {} to {} ({})",
+            project,
+            installed.current_version,
+            block.version,
+            final_bin.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use crate::{
+    core::{
+        AnvilCore,
+        cmd::{anvil_home, install::InstallMeta},
+    },
+    store::traits::Store,
+};
+
+impl<S: Store> AnvilCore<S> {
+    pub fn uninstall(&mut self, project: &str, purge: bool) -> anyhow::Result<()> {
+        let installed = InstallMeta::load(project)?;
+
+        for link in InstallMeta::links(project)? {
+            std::fs::remove_file(link)?;
+        }
+        // plain binary left by an anvil predating versioned installs
+        let link = anvil_home()?.join("bin").join(project);
+        if link.symlink_metadata().is_ok_and(|m| m.is_file()) {
+            std::fs::remove_file(&link)?;
+        }
+
+        let versions_dir = InstallMeta::versions_dir(project)?;
+        if versions_dir.exists() {
+            std::fs::remove_dir_all(&versions_dir)?;
+        }
+
+        std::fs::remove_file(InstallMeta::path(project)?)?;
+
+        if purge && installed.local_repo_path.exists() {
+            std::fs::remove_dir_all(&installed.local_repo_path)?;
+            println!("Removed {}", installed.local_repo_path.display());
+        }
+
+        let removed = self.release_cached_objects(&installed.cached_objects)?;
+
+        println!(
+            "Uninstalled {} ({}), {} cached artefact(s) removed",
+            project, installed.current_version, removed
+        );
+        Ok(())
+    }
+
+    /// Drop the objects an install fetched into the local store, unless a store
+    /// index or another installed project still refers to them.
+    ///
+    /// Objects written by `pack` are indexed and never fetched, so they are kept.
+    pub fn release_cached_objects(&self, cached: &[String]) -> anyhow::Result<usize> {
+        if cached.is_empty() {
+            return Ok(0);
+        }
+        let _lock = self.store.lock()?;
+
+        let mut referenced = HashSet::new();
+        for entry in self.store.index_entries()? {
+            // members of an indexed manifest are only reachable through it
+            if let Ok(Some(manifest)) = self.store.get_manifest(&entry.artefact_hash) {
+                referenced.extend(manifest.artifacts.into_iter().map(|a| a.artefact_hash));
+            }
+            referenced.insert(entry.artefact_hash);
+        }
+        for (_, installed) in InstallMeta::all()? {
+            referenced.extend(installed.cached_objects);
+        }
+
+        let mut removed = 0;
+        for hash in cached {
+            if !referenced.contains(hash) && self.store.exists_locally(hash) {
+                self.store.remove_object(hash)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
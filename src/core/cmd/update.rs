@@ -0,0 +1,61 @@
+use crate::{
+    core::{AnvilCore, cmd::install::InstallMeta},
+    store::traits::Store,
+};
+
+impl<S: Store> AnvilCore<S> {
+    /// Move `project` (or every installed project when `None`) to its newest block.
+    ///
+    /// Each project is installed from the store `open_store` gives for it.
+    pub fn update(
+        project: Option<&str>,
+        check: bool,
+        force: bool,
+        open_store: impl Fn(&str) -> anyhow::Result<S>,
+    ) -> anyhow::Result<()> {
+        let targets = match project {
+            Some(name) => vec![(name.to_string(), InstallMeta::load(name)?)],
+            None => InstallMeta::all()?,
+        };
+
+        let mut outdated = 0;
+        for (name, installed) in targets {
+            Self::ensure_repo_cloned(&installed.repo_url, &installed.local_repo_path)?;
+
+            let blocks = Self::load_trusted_blocks(&name, &installed.local_repo_path)?;
+            let latest = Self::resolve_version(&blocks, None)?;
+
+            if installed.is_current(latest) {
+                println!("{name} is up to date ({})", installed.current_version);
+                continue;
+            }
+
+            outdated += 1;
+            println!("{name}: {} → {}", installed.current_version, latest.version);
+
+            if !check {
+                let mut core = Self::without_project(open_store(&name)?)?;
+                let final_bin = core.install_block(
+                    &name,
+                    &installed.repo_url,
+                    &installed.local_repo_path,
+                    &blocks,
+                    latest,
+                    false,
+                    force,
+                )?;
+                println!(
+                    "Updated {} to {} at {}",
+                    name,
+                    latest.version,
+                    final_bin.display()
+                );
+            }
+        }
+
+        if check && outdated > 0 {
+            anyhow::bail!("{outdated} update(s) available");
+        }
+        Ok(())
+    }
+}
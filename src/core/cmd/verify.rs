@@ -0,0 +1,64 @@
+use crate::{core::AnvilCore, store::traits::Store};
+
+impl<S: Store> AnvilCore<S> {
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let problems = self.verify_problems();
+
+        if problems.is_empty() {
+            println!("Chain verified: {} block(s) OK", self.blocks.len());
+            return Ok(());
+        }
+
+        for problem in &problems {
+            eprintln!("error: {problem}");
+        }
+        anyhow::bail!("Verification failed: {} problem(s) found", problems.len())
+    }
+
+    /// Chain problems followed by missing or corrupted artefacts in the store.
+    pub fn verify_problems(&self) -> Vec<String> {
+        let mut problems = self.chain_problems();
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            match self.store.block_artifact(&block.block_hash) {
+                Ok(Some(indexed)) if indexed != block.artefact_hash => problems.push(format!(
+                    "block {} ({}): store indexes artefact {} instead of {}",
+                    i, block.version, indexed, block.artefact_hash
+                )),
+                Err(e) => problems.push(format!(
+                    "block {} ({}): cannot read store index: {e}",
+                    i, block.version
+                )),
+                _ => {}
+            }
+
+            if !self.store.exists(&block.artefact_hash) {
+                problems.push(format!(
+                    "block {} ({}): artefact missing from store",
+                    i, block.version
+                ));
+                continue;
+            }
+
+            // reading the object checks its bytes against the artefact hash
+            if let Err(e) = self.store.get_artifact(&block.artefact_hash) {
+                problems.push(format!("block {} ({}): {e}", i, block.version));
+            }
+
+            for artifact in &block.manifest {
+                if !self.store.exists(&artifact.artefact_hash) {
+                    problems.push(format!(
+                        "block {} ({}): artifact {} missing from store",
+                        i, block.version, artifact.name
+                    ));
+                } else if let Err(e) = self.store.get_artifact(&artifact.artefact_hash) {
+                    problems.push(format!(
+                        "block {} ({}): artifact {}: {e}",
+                        i, block.version, artifact.name
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
@@ -1,14 +1,21 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 pub mod cmd;
 pub mod tests;
 
 use crate::{
     cli::{Cli, Commands},
-    config::Config,
+    config::{Config, UserConfig},
     store::{
+        atomic::atomic_write,
         fs_store::FsStore,
-        meta::{Meta, get_last_commit},
+        layered::{LayeredStore, is_url, open_store},
+        lock::{FileLock, set_lock_timeout},
+        meta::{HASH_VERSION, Manifest, Meta, get_last_commit},
         traits::Store,
     },
 };
@@ -38,15 +45,9 @@ impl<S: Store> AnvilCore<S> {
             fs::create_dir_all(&anvil_dir)?;
         }
 
-        let blocks_path = anvil_dir.join("blocks.json");
-        let blocks = if blocks_path.exists() {
-            let content = fs::read_to_string(&blocks_path)?;
-            serde_json::from_str(&content)?
-        } else {
-            Vec::new()
-        };
+        let blocks = Self::read_blocks(&anvil_dir.join("blocks.json"))?;
 
-        let last_commit = get_last_commit()?;
+        let last_commit = get_last_commit(&project_root)?;
 
         Ok(Self {
             config: config.unwrap_or_default(),
@@ -57,6 +58,37 @@ impl<S: Store> AnvilCore<S> {
         })
     }
 
+    /// Core for commands acting on installed projects only, which neither read
+    /// nor create a project chain in the current directory.
+    pub fn without_project(store: S) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: Config::default(),
+            store,
+            blocks: Vec::new(),
+            current_commit: None,
+            project_root: cmd::anvil_home()?,
+        })
+    }
+
+    fn read_blocks(blocks_path: &Path) -> anyhow::Result<Vec<Meta>> {
+        if !blocks_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(blocks_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Take the project chain lock and reload the blocks under it.
+    ///
+    /// Commands appending to the chain must hold the returned guard until they
+    /// saved, so concurrent runs cannot drop each other's blocks. Plain readers
+    /// do not need it since `blocks.json` is replaced atomically.
+    pub fn lock_chain(&mut self) -> anyhow::Result<FileLock> {
+        let lock = FileLock::acquire(&self.anvil_dir().join("blocks.lock"))?;
+        self.blocks = Self::read_blocks(&self.blocks_file())?;
+        Ok(lock)
+    }
+
     fn anvil_dir(&self) -> PathBuf {
         self.project_root.join(".anvil")
     }
@@ -67,7 +99,7 @@ impl<S: Store> AnvilCore<S> {
 
     pub fn save_blocks(&self) -> anyhow::Result<()> {
         let blocks_json = serde_json::to_string_pretty(&self.blocks)?;
-        fs::write(self.blocks_file(), blocks_json)?;
+        atomic_write(&self.blocks_file(), blocks_json.as_bytes())?;
         Ok(())
     }
 
@@ -75,48 +107,217 @@ impl<S: Store> AnvilCore<S> {
         self.blocks.is_empty()
     }
 
-    pub fn validate_chain(&self) -> anyhow::Result<()> {
-        for i in 1..self.blocks.len() {
-            let prev = &self.blocks[i - 1];
-            let curr = &self.blocks[i];
+    /// Every linkage and block hash problem in the chain, instead of just the first one.
+    pub fn chain_problems(&self) -> Vec<String> {
+        Self::blocks_problems(&self.blocks)
+    }
 
-            if curr.prev_block_hash.as_deref() != Some(&prev.block_hash) {
-                return Err(anyhow::anyhow!(
-                    "Invalid chain: block {} does not correctly reference previous block {}",
+    pub fn blocks_problems(blocks: &[Meta]) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (i, curr) in blocks.iter().enumerate() {
+            if i == 0 {
+                if let Some(prev) = &curr.prev_block_hash {
+                    problems.push(format!(
+                        "genesis block ({}) references a previous block {}",
+                        curr.version, prev
+                    ));
+                }
+            } else if curr.prev_block_hash.as_deref() != Some(&blocks[i - 1].block_hash) {
+                problems.push(format!(
+                    "block {} ({}) does not correctly reference previous block {}",
                     i,
-                    i - 1,
+                    curr.version,
+                    i - 1
+                ));
+            }
+
+            if curr.hash_version > HASH_VERSION {
+                problems.push(format!(
+                    "block {} ({}) uses hash version {}, this anvil only supports up to {}",
+                    i, curr.version, curr.hash_version, HASH_VERSION
+                ));
+                continue;
+            }
+
+            if !curr.manifest.is_empty()
+                && S::compute_hash(&Manifest::new(&curr.manifest).to_bytes()) != curr.artefact_hash
+            {
+                problems.push(format!(
+                    "block {} ({}) has a manifest that does not match its artefact hash",
+                    i, curr.version
                 ));
             }
 
             let expected_hash = S::compute_block_hash(curr);
             if expected_hash != curr.block_hash {
-                return Err(anyhow::anyhow!(
-                    "Invalid block hash for block {}: expected {}, found {}",
-                    i,
-                    expected_hash,
-                    curr.block_hash
+                problems.push(format!(
+                    "block {} ({}) has an invalid hash: expected {}, found {}",
+                    i, curr.version, expected_hash, curr.block_hash
                 ));
             }
         }
-        Ok(())
+        problems
+    }
+
+    pub fn validate_chain(&self) -> anyhow::Result<()> {
+        match self.chain_problems().into_iter().next() {
+            Some(problem) => Err(anyhow::anyhow!("Invalid chain: {problem}")),
+            None => Ok(()),
+        }
     }
 }
 
+/// The shared local store at `~/.anvil/store`, moved out of the old per-project layout first.
+fn project_store(name: &str) -> anyhow::Result<FsStore> {
+    let store = FsStore::new(FsStore::get_path(".anvil/store"), name)?;
+    store.migrate_project_stores()?;
+    Ok(store)
+}
+
+/// Stores configured for a project: the ones of `config` first, then the user level ones.
+fn configured_stores(config: Option<&Config>) -> anyhow::Result<Vec<String>> {
+    let mut stores = config.map(|c| c.stores.clone()).unwrap_or_default();
+    stores.extend(UserConfig::load()?.stores);
+    Ok(stores)
+}
+
+/// The local store of an installed project in front of its configured remotes.
+///
+/// Project level stores are read from the cached repo, when already cloned. The
+/// repo is not trusted with the local filesystem, so only its urls are used:
+/// directories are only opened when the user config lists them.
+fn installed_store(name: &str) -> anyhow::Result<LayeredStore<FsStore>> {
+    let repo_config = cmd::anvil_home()?
+        .join("repo")
+        .join(name)
+        .join(".anvil/anvil.yml");
+    let repo_stores = if repo_config.exists() {
+        Config::new(Some(&repo_config))?.stores
+    } else {
+        Vec::new()
+    };
+    let user_stores = UserConfig::load()?.stores;
+
+    let mut locations = Vec::new();
+    for location in repo_stores {
+        if is_url(&location) {
+            locations.push(location);
+        } else if !user_stores.contains(&location) {
+            eprintln!(
+                "warning: ignoring store {location} of {name}, list it in ~/.anvil/config.yml to use it"
+            );
+        }
+    }
+    locations.extend(user_stores);
+
+    let mut store = LayeredStore::new(project_store(name)?);
+    for location in locations {
+        store = store.with_remote(open_store(&location, name)?);
+    }
+    Ok(store)
+}
+
+/// `remote` when given, else the first store configured for the current project.
+fn remote_store(name: &str, remote: Option<&str>) -> anyhow::Result<Box<dyn Store>> {
+    let location = match remote {
+        Some(remote) => remote.to_string(),
+        None => {
+            let config = if Path::new(".anvil/anvil.yml").exists() {
+                Some(Config::new(None)?)
+            } else {
+                None
+            };
+            configured_stores(config.as_ref())?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("No remote given and no store configured"))?
+        }
+    };
+    open_store(&location, name)
+}
+
 pub fn interpret(cli: &Cli) -> anyhow::Result<()> {
-    let name = get_project_name()?;
-    let store_path = FsStore::get_path(&format!(".anvil/store/{name}"));
-    let store = FsStore::new(store_path)?;
+    if let Some(secs) = cli.lock_timeout {
+        set_lock_timeout(Duration::from_secs(secs));
+    }
     match &cli.command {
-        Commands::Pack { v, tag } => {
+        Commands::Pack {
+            v,
+            tag,
+            allow_dirty,
+        } => {
             let config = Config::new(None)?;
-            AnvilCore::new(Some(config), store, env::current_dir()?)?.pack(v, *tag)
+            let store = project_store(&get_project_name()?)?.with_compression(config.compression);
+            AnvilCore::new(Some(config), store, env::current_dir()?)?.pack(v, *tag, *allow_dirty)
         }
-        Commands::Install { url, version } => {
-            AnvilCore::new(None, store, env::current_dir()?)?.install(url, version.clone())
+        Commands::Install {
+            url,
+            version,
+            build_from_source,
+            force,
+        } => {
+            // the configured stores are only known once the repo is cloned
+            let store =
+                LayeredStore::new(project_store(&cmd::install::extract_project_name(url)?)?);
+            AnvilCore::without_project(store)?.install(
+                url,
+                version.clone(),
+                *build_from_source,
+                *force,
+                installed_store,
+            )
         }
         Commands::Switch {
-            project: _,
-            version: _,
-        } => Ok(()),
+            project,
+            version,
+            force,
+        } => {
+            let store = installed_store(project)?;
+            AnvilCore::without_project(store)?.switch(project, version, *force)
+        }
+        Commands::Uninstall { project, purge } => {
+            let store = project_store(project)?;
+            AnvilCore::without_project(store)?.uninstall(project, *purge)
+        }
+        Commands::Update {
+            project,
+            all: _,
+            check,
+            force,
+        } => AnvilCore::update(project.as_deref(), *check, *force, installed_store),
+        Commands::Log {
+            range,
+            oneline,
+            json,
+        } => {
+            let store = project_store(&get_project_name()?)?;
+            AnvilCore::new(None, store, env::current_dir()?)?.log(*oneline, *json, range.as_deref())
+        }
+        Commands::Verify => {
+            let store = project_store(&get_project_name()?)?;
+            AnvilCore::new(None, store, env::current_dir()?)?.verify()
+        }
+        Commands::Migrate => {
+            let store = project_store(&get_project_name()?)?;
+            AnvilCore::new(None, store, env::current_dir()?)?.migrate()
+        }
+        Commands::Gc { dry_run, keep_last } => {
+            let name = get_project_name()?;
+            let store = project_store(&name)?;
+            AnvilCore::new(None, store, env::current_dir()?)?.gc(&name, *keep_last, *dry_run)
+        }
+        Commands::Push { remote } => {
+            let name = get_project_name()?;
+            let store = project_store(&name)?;
+            AnvilCore::new(None, store, env::current_dir()?)?
+                .push(remote_store(&name, remote.as_deref())?.as_ref())
+        }
+        Commands::Pull { remote } => {
+            let name = get_project_name()?;
+            let store = project_store(&name)?;
+            AnvilCore::new(None, store, env::current_dir()?)?
+                .pull(remote_store(&name, remote.as_deref())?.as_ref())
+        }
+        Commands::List { all, json } => cmd::list::list(*all, *json),
     }
 }
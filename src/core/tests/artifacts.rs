@@ -0,0 +1,181 @@
+#[test]
+fn test_pack_multiple_artifacts() {
+    use crate::{
+        config::Artifact,
+        store::{meta::ArtefactType, mock::MockStore, traits::Store},
+    };
+
+    // collecting garbage reads the chains of installed projects
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::artifacts::test_pack_multiple_artifacts",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.command = "echo".to_string();
+    for (path, name, artefact_type) in [
+        ("out/cli", "tool", ArtefactType::Bin),
+        ("out/daemon", "toold", ArtefactType::Bin),
+        ("out/libtool.so", "libtool.so", ArtefactType::Lib),
+    ] {
+        anvil.config.build.artifacts.push(Artifact {
+            path: path.into(),
+            name: name.to_string(),
+            artefact_type,
+            entrypoint: None,
+        });
+    }
+
+    std::fs::create_dir_all(temp.path().join("out")).unwrap();
+    for (file, content) in [("cli", "cli"), ("daemon", "daemon"), ("libtool.so", "lib")] {
+        std::fs::write(temp.path().join("out").join(file), content).unwrap();
+    }
+    anvil.pack("0.0.1", false, false).unwrap();
+
+    let block = anvil.blocks[0].clone();
+    assert_eq!(block.artefact_type, ArtefactType::Manifest);
+    assert_eq!(block.manifest.len(), 3);
+    assert_eq!(block.object_hashes().len(), 4);
+    assert_eq!(block.artifacts("tool"), block.manifest);
+    assert_eq!(anvil.store.list_objects().unwrap().len(), 4);
+    assert!(anvil.verify_problems().is_empty());
+
+    // every artifact is kept alive by the block
+    let report = anvil.collect_garbage("mock", None, true).unwrap();
+    assert!(report.removed_objects.is_empty());
+
+    // a missing artifact is reported
+    anvil
+        .store
+        .remove_object(&block.manifest[1].artefact_hash)
+        .unwrap();
+    assert_eq!(anvil.verify_problems().len(), 1);
+
+    // the manifest is covered by the artefact hash
+    anvil.blocks[0].manifest[2].artefact_hash = block.manifest[0].artefact_hash.clone();
+    assert!(anvil.validate_chain().is_err());
+}
+
+#[test]
+fn test_pack_artifact_dir() {
+    use crate::store::{archive::unpack_dir, meta::ArtefactType, mock::MockStore, traits::Store};
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.project.name = "tool".to_string();
+    anvil.config.build.command = "echo".to_string();
+    anvil.config.build.pack = true;
+    anvil.config.build.artifact_dir = "out".into();
+    anvil.config.build.entrypoint = "out/bin/tool".into();
+
+    let out = temp.path().join("out");
+    std::fs::create_dir_all(out.join("bin")).unwrap();
+    std::fs::create_dir_all(out.join("share")).unwrap();
+    std::fs::write(out.join("bin/tool"), "#!/bin/sh").unwrap();
+    std::fs::set_permissions(out.join("bin/tool"), std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::write(out.join("share/data.txt"), "data").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+
+    let block = anvil.blocks[0].clone();
+    assert_eq!(block.manifest.len(), 1);
+    let artifact = &block.manifest[0];
+    assert_eq!(artifact.artefact_type, ArtefactType::Pack);
+    assert_eq!(artifact.name, "tool");
+    assert_eq!(artifact.entrypoint.as_deref(), Some("bin/tool"));
+    assert!(anvil.verify_problems().is_empty());
+
+    // the archive holds the whole directory
+    let bytes = anvil.store.get_artifact(&artifact.artefact_hash).unwrap();
+    let dest = temp.path().join("installed");
+    unpack_dir(&bytes, &dest).unwrap();
+    assert_eq!(std::fs::read(dest.join("share/data.txt")).unwrap(), b"data");
+
+    // packing the same tree again is a no-op
+    std::fs::write(out.join("share/data.txt"), "data").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+    assert_eq!(anvil.blocks.len(), 1);
+}
+
+#[test]
+fn test_artifacts_cannot_escape() {
+    use crate::{
+        config::Artifact,
+        core::AnvilCore,
+        store::{
+            meta::{ArtefactType, Manifest, ManifestEntry},
+            mock::MockStore,
+            traits::Store,
+        },
+    };
+
+    let entry = |name: &str, path: &str, entrypoint: Option<&str>| ManifestEntry {
+        name: name.to_string(),
+        path: path.to_string(),
+        artefact_type: ArtefactType::Bin,
+        artefact_hash: String::new(),
+        entrypoint: entrypoint.map(String::from),
+    };
+    assert!(entry("tool", "out/tool", None).check().is_ok());
+    assert!(entry("tool", "./out", Some("bin/tool")).check().is_ok());
+    for bad in [
+        entry("../../.bashrc", "out/tool", None),
+        entry("/abs", "out/tool", None),
+        entry("bin/tool", "out/tool", None),
+        entry("..", "out/tool", None),
+        entry("tool", "/etc/x", None),
+        entry("tool", "out/../../x", None),
+        entry("tool", "out", Some("../../x")),
+    ] {
+        assert!(bad.check().is_err(), "{bad:?}");
+    }
+
+    // pack refuses them
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let mut anvil = AnvilCore::new(
+        Some(crate::config::Config::default()),
+        store,
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    anvil.config.build.command = "echo".to_string();
+    anvil.config.build.artifacts.push(Artifact {
+        path: "tool".into(),
+        name: "../tool".to_string(),
+        artefact_type: ArtefactType::Bin,
+        entrypoint: None,
+    });
+    std::fs::write(temp.path().join("tool"), "tool").unwrap();
+    let err = anvil.pack("0.0.1", false, false).unwrap_err().to_string();
+    assert!(err.contains("Invalid artifact name"), "{err}");
+    assert!(anvil.blocks.is_empty());
+
+    // and so does install, even for a consistently rehashed remote chain
+    anvil.config.build.artifacts[0].name = "tool".to_string();
+    anvil.pack("0.0.1", false, false).unwrap();
+    type Core = AnvilCore<MockStore>;
+    assert!(Core::check_remote_chain(&anvil.blocks, None).is_ok());
+
+    let mut forged = anvil.blocks.clone();
+    forged[0].manifest[0].path = "/etc/passwd".to_string();
+    forged[0].artefact_hash =
+        MockStore::compute_hash(&Manifest::new(&forged[0].manifest).to_bytes());
+    forged[0].block_hash = MockStore::compute_block_hash(&forged[0]);
+    let err = Core::check_remote_chain(&forged, None)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("/etc/passwd"), "{err}");
+}
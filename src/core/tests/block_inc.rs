@@ -25,9 +25,44 @@ fn test_pack_reuse_block() {
     anvil.config.build.entrypoint = fake_bin;
     anvil.config.build.command = "echo Build".to_string();
 
-    anvil.pack("0.0.1", false).unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
     assert_eq!(anvil.blocks.len(), 1);
 
-    anvil.pack("0.0.2", false).unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
     assert_eq!(anvil.blocks.len(), 1);
 }
+
+#[test]
+fn test_concurrent_pack_keeps_every_block() {
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            let root = temp_dir.path().to_path_buf();
+            std::thread::spawn(move || {
+                let store = crate::store::mock::MockStore::new(root.to_string_lossy().to_string());
+                let mut anvil = crate::core::AnvilCore::new(
+                    Some(crate::config::Config::default()),
+                    store,
+                    root.clone(),
+                )
+                .unwrap();
+
+                let fake_bin = root.join(format!("fake_bin_{i}"));
+                std::fs::write(&fake_bin, format!("build {i}")).unwrap();
+                anvil.config.build.entrypoint = fake_bin;
+                anvil.config.build.command = "echo Build".to_string();
+
+                anvil.pack(&format!("0.0.{i}"), false, false).unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let store = crate::store::mock::MockStore::new(temp_dir.path().to_string_lossy().to_string());
+    let anvil = crate::core::AnvilCore::new(None, store, temp_dir.path().to_path_buf()).unwrap();
+    assert_eq!(anvil.blocks.len(), 4);
+    assert!(anvil.validate_chain().is_ok());
+}
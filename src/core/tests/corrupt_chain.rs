@@ -1,29 +1,30 @@
-// #[test]
-// fn test_missing_block_in_chain() {
-//     let temp = tempfile::tempdir().unwrap();
-//     let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
-//     let mut config = crate::config::Config::default();
+#[test]
+fn test_missing_block_in_chain() {
+    let temp = tempfile::tempdir().unwrap();
+    let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
 
-//     let fake_bin = temp.path().join("bin");
-//     config.build.entrypoint = fake_bin.clone();
-//     config.build.command = "echo build".to_string();
+    let fake_bin = temp.path().join("bin");
 
-//     let mut anvil = crate::core::AnvilCore::new(config, store, temp.path().to_path_buf()).unwrap();
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo build".to_string();
 
-//     // genesis (block 0)
-//     std::fs::write(&fake_bin, b"hello").unwrap();
-//     anvil.pack().unwrap();
-//     assert_eq!(anvil.blocks.len(), 1);
+    // genesis (block 0)
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    assert_eq!(anvil.blocks.len(), 1);
 
-//     // block 1
-//     std::fs::write(&fake_bin, b"world").unwrap();
-//     anvil.pack().unwrap();
-//     assert_eq!(anvil.blocks.len(), 2);
+    // block 1
+    std::fs::write(&fake_bin, b"world").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+    assert_eq!(anvil.blocks.len(), 2);
 
-//     anvil.blocks.remove(0);
+    anvil.blocks.remove(0);
 
-//     assert!(anvil.validate_chain().is_err());
-// }
+    assert!(anvil.validate_chain().is_err());
+}
 
 #[test]
 fn test_invalid_prev_block_hash() {
@@ -39,34 +40,62 @@ fn test_invalid_prev_block_hash() {
     anvil.config.build.command = "echo".to_string();
 
     std::fs::write(&fake_bin, b"hello").unwrap();
-    anvil.pack("0.0.1", false).unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
     std::fs::write(&fake_bin, b"world").unwrap();
-    anvil.pack("0.0.2", false).unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
 
     anvil.blocks[1].prev_block_hash = Some("FAKE_PREV_HASH".into());
 
     assert!(anvil.validate_chain().is_err());
 }
 
-// #[test]
-// fn test_invalid_block_hash() {
-//     let temp = tempfile::tempdir().unwrap();
-//     let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
-//     let config = crate::config::Config::default();
+#[test]
+fn test_invalid_block_hash() {
+    let temp = tempfile::tempdir().unwrap();
+    let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
 
-//     let fake_bin = temp.path().join("bin");
-//     std::fs::write(&fake_bin, b"hello").unwrap();
+    let fake_bin = temp.path().join("bin");
+    std::fs::write(&fake_bin, b"hello").unwrap();
 
-//     let mut anvil = crate::core::AnvilCore::new(config, store, temp.path().to_path_buf()).unwrap();
-//     anvil.config.build.entrypoint = fake_bin.clone();
-//     anvil.config.build.command = "echo".to_string();
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
 
-//     anvil.pack().unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    assert!(anvil.validate_chain().is_ok());
 
-//     anvil.blocks[0].block_hash = "WRONG_HASH".into();
+    anvil.blocks[0].block_hash = "WRONG_HASH".into();
 
-//     assert!(anvil.validate_chain().is_err());
-// }
+    assert!(anvil.validate_chain().is_err());
+}
+
+#[test]
+fn test_genesis_with_prev_block_hash() {
+    use crate::store::traits::Store;
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+    std::fs::write(&fake_bin, b"hello").unwrap();
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    anvil.pack("0.0.1", false, false).unwrap();
+
+    // even with a consistent block hash, a genesis block must not have a parent
+    anvil.blocks[0].prev_block_hash = Some("FAKE_PREV_HASH".into());
+    anvil.blocks[0].block_hash =
+        crate::store::mock::MockStore::compute_block_hash(&anvil.blocks[0]);
+
+    assert!(anvil.validate_chain().is_err());
+}
 
 #[test]
 fn test_block_out_of_order() {
@@ -82,11 +111,11 @@ fn test_block_out_of_order() {
     anvil.config.build.command = "echo".to_string();
 
     std::fs::write(&fake_bin, b"hello").unwrap();
-    anvil.pack("0.0.1", false).unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
     std::fs::write(&fake_bin, b"wonderful").unwrap();
-    anvil.pack("0.0.2", false).unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
     std::fs::write(&fake_bin, b"world").unwrap();
-    anvil.pack("0.0.3", false).unwrap();
+    anvil.pack("0.0.3", false, false).unwrap();
 
     anvil.blocks.reverse();
 
@@ -0,0 +1,57 @@
+#[test]
+fn test_gc_removes_unreferenced_objects() {
+    use crate::store::{mock::MockStore, traits::Store};
+
+    // collecting garbage reads the chains of installed projects
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::gc::test_gc_removes_unreferenced_objects",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    for (i, content) in ["hello", "wonderful", "world"].iter().enumerate() {
+        std::fs::write(&fake_bin, content).unwrap();
+        anvil.pack(&format!("0.0.{i}"), false, false).unwrap();
+    }
+    anvil.store.corrupt("orphan", b"left behind");
+
+    // dry run only reports
+    let report = anvil.collect_garbage("mock", None, true).unwrap();
+    assert_eq!(report.removed_objects, vec![("orphan".to_string(), 11)]);
+    assert_eq!(report.reclaimed(), 11);
+    assert!(anvil.store.exists("orphan"));
+
+    let report = anvil.collect_garbage("mock", None, false).unwrap();
+    assert_eq!(report.removed_objects.len(), 1);
+    assert!(!anvil.store.exists("orphan"));
+    assert!(anvil.verify_problems().is_empty());
+
+    // only the newest block of the chain survives
+    let report = anvil.collect_garbage("mock", Some(1), false).unwrap();
+    assert_eq!(report.removed_entries.len(), 2);
+    assert_eq!(report.removed_objects.len(), 2);
+    assert!(anvil.store.exists(&anvil.blocks[2].artefact_hash));
+    assert!(!anvil.store.exists(&anvil.blocks[0].artefact_hash));
+}
+
+#[test]
+fn test_format_size() {
+    use crate::core::cmd::gc::format_size;
+
+    assert_eq!(format_size(12), "12 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+}
@@ -0,0 +1,208 @@
+#[test]
+fn test_install_prefers_prebuilt_artefacts() {
+    use crate::{
+        core::{AnvilCore, cmd::anvil_home, tests::RemoteProject},
+        store::{fs_store::FsStore, traits::Store},
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::install::test_install_prefers_prebuilt_artefacts",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let remote = RemoteProject::new(temp.path());
+    let block = remote.pack("0.1.0", b"prebuilt");
+    remote.publish();
+    let bin = anvil_home().unwrap().join("bin/tool");
+
+    // the packed artefact is in the store
+    let packed = |name: &str| FsStore::new(&remote.store, name);
+    let mut anvil = AnvilCore::without_project(packed("tool").unwrap()).unwrap();
+    anvil
+        .install(&remote.url(), None, false, false, packed)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"prebuilt");
+
+    // forced source build of the same block
+    std::fs::remove_dir_all(anvil_home().unwrap().join("versions")).unwrap();
+    anvil
+        .install(&remote.url(), None, true, false, packed)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"built");
+
+    // a store without the artefact
+    std::fs::remove_dir_all(anvil_home().unwrap().join("versions")).unwrap();
+    let empty = |name: &str| FsStore::new(temp.path().join("empty"), name);
+    anvil
+        .install(&remote.url(), None, false, false, empty)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"built");
+
+    // a corrupted artefact is not installed
+    std::fs::remove_dir_all(anvil_home().unwrap().join("versions")).unwrap();
+    let store = packed("tool").unwrap();
+    std::fs::write(store.object_path(&block.artefact_hash), b"tampered").unwrap();
+    assert!(store.exists(&block.artefact_hash));
+    anvil
+        .install(&remote.url(), None, false, false, packed)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"built");
+}
+
+#[test]
+fn test_install_uses_project_stores() {
+    use crate::{
+        core::{
+            AnvilCore,
+            cmd::{anvil_home, install::InstallMeta},
+            installed_store, project_store,
+            tests::RemoteProject,
+        },
+        store::{layered::LayeredStore, traits::Store},
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::install::test_install_uses_project_stores",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let remote = RemoteProject::new(temp.path());
+    let block = remote.pack("0.1.0", b"prebuilt");
+    remote.publish();
+    let bin = anvil_home().unwrap().join("bin/tool");
+
+    // nothing is cloned yet, so the local store knows no remote
+    let store = LayeredStore::new(project_store("tool").unwrap());
+    let mut anvil = AnvilCore::without_project(store).unwrap();
+
+    // a directory named by the repo alone is not opened
+    let packed = remote.store.with_extension("bak");
+    std::fs::rename(&remote.store, &packed).unwrap();
+    anvil
+        .install(&remote.url(), None, false, false, installed_store)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"built");
+    assert!(!remote.store.exists());
+
+    // it is once the user config lists it too
+    std::fs::rename(&packed, &remote.store).unwrap();
+    std::fs::remove_dir_all(anvil_home().unwrap().join("versions")).unwrap();
+    std::fs::write(
+        anvil_home().unwrap().join("config.yml"),
+        format!("stores: [{}]\n", remote.store.display()),
+    )
+    .unwrap();
+    anvil
+        .install(&remote.url(), None, false, false, installed_store)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"prebuilt");
+    assert!(anvil.store.local().exists(&block.artefact_hash));
+    assert_eq!(
+        InstallMeta::load("tool").unwrap().cached_objects,
+        [block.artefact_hash]
+    );
+}
+
+#[test]
+fn test_install_keeps_foreign_executables() {
+    use crate::{
+        core::{AnvilCore, cmd::anvil_home, tests::RemoteProject},
+        store::fs_store::FsStore,
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::install::test_install_keeps_foreign_executables",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let remote = RemoteProject::new(temp.path());
+    remote.pack("0.1.0", b"prebuilt");
+    remote.publish();
+    let packed = |name: &str| FsStore::new(&remote.store, name);
+    let mut anvil = AnvilCore::without_project(packed("tool").unwrap()).unwrap();
+
+    // a binary anvil did not install
+    let bin = anvil_home().unwrap().join("bin/tool");
+    crate::core::cmd::replace_executable(&bin, b"mine").unwrap();
+    let err = anvil
+        .install(&remote.url(), None, false, false, packed)
+        .unwrap_err();
+    assert!(err.to_string().contains("was not installed by anvil"));
+    assert_eq!(std::fs::read(&bin).unwrap(), b"mine");
+
+    // the link of another project
+    let other = anvil_home().unwrap().join("versions/other/abc/tool");
+    crate::core::cmd::replace_executable(&other, b"other").unwrap();
+    crate::core::cmd::replace_symlink(&other, &bin).unwrap();
+    let err = anvil
+        .install(&remote.url(), None, false, false, packed)
+        .unwrap_err();
+    assert!(err.to_string().contains("is installed by other"));
+    assert_eq!(std::fs::read(&bin).unwrap(), b"other");
+
+    anvil
+        .install(&remote.url(), None, false, true, packed)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"prebuilt");
+
+    // its own link is replaced without forcing
+    anvil
+        .install(&remote.url(), None, true, false, packed)
+        .unwrap();
+    assert_eq!(std::fs::read(&bin).unwrap(), b"built");
+}
+
+#[test]
+fn test_install_block_packed_outside_git() {
+    use crate::{
+        core::{AnvilCore, cmd::anvil_home, tests::RemoteProject},
+        store::{fs_store::FsStore, traits::Store},
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::install::test_install_block_packed_outside_git",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let remote = RemoteProject::new(temp.path());
+    let mut block = remote.pack("0.1.0", b"prebuilt");
+    block.git_commit = String::new();
+    block.block_hash = FsStore::compute_block_hash(&block);
+    std::fs::write(
+        remote.dir.join(".anvil/blocks.json"),
+        serde_json::to_string_pretty(&[&block]).unwrap(),
+    )
+    .unwrap();
+    remote.publish();
+
+    // there is no commit to build it from
+    let empty = |name: &str| FsStore::new(temp.path().join("empty"), name);
+    let mut anvil = AnvilCore::without_project(empty("tool").unwrap()).unwrap();
+    let err = anvil
+        .install(&remote.url(), None, false, false, empty)
+        .unwrap_err();
+    assert!(err.to_string().contains("was packed outside git"));
+
+    let packed = |name: &str| FsStore::new(&remote.store, name);
+    anvil
+        .install(&remote.url(), None, false, false, packed)
+        .unwrap();
+    let bin = anvil_home().unwrap().join("bin/tool");
+    assert_eq!(std::fs::read(bin).unwrap(), b"prebuilt");
+}
@@ -0,0 +1,34 @@
+#[test]
+fn test_render_table_marks_active_version() {
+    use crate::core::cmd::list::{ListEntry, VersionEntry, render_table};
+
+    let entries = vec![ListEntry {
+        name: "anvil".into(),
+        repo_url: "https://github.com/pepedinho/anvil".into(),
+        current_version: "0.2.0".into(),
+        current_commit: "1f6cfed2c78c70b7e82dc3c4f240247e8c8f8007".into(),
+        install_path: "/home/user/.anvil/bin/anvil".into(),
+        versions: Some(vec![
+            VersionEntry {
+                version: "0.1.0".into(),
+                block_hash: "a9df9c3dfafaa121".into(),
+                git_commit: "c00b04bb40796926".into(),
+                active: false,
+            },
+            VersionEntry {
+                version: "0.2.0".into(),
+                block_hash: "be677ac6282dd394".into(),
+                git_commit: "1f6cfed2c78c70b7".into(),
+                active: true,
+            },
+        ]),
+    }];
+
+    let table = render_table(&entries);
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert!(lines[0].starts_with("NAME   VERSION  COMMIT"));
+    assert!(lines[1].starts_with("anvil  0.2.0    1f6cfed2"));
+    assert_eq!(lines[2], "    0.1.0      a9df9c3d (c00b04bb)");
+    assert_eq!(lines[3], "  * 0.2.0      be677ac6 (1f6cfed2)");
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+fn block(version: &str, secs: u64) -> crate::store::meta::Meta {
+    use std::time::{Duration, UNIX_EPOCH};
+    crate::store::meta::Meta {
+        artefact_hash: format!("artefact-{version}"),
+        artefact_type: crate::store::meta::ArtefactType::Bin,
+        created_at: UNIX_EPOCH + Duration::from_secs(secs),
+        version: version.to_string(),
+        git_commit: format!("c{}{}", version.replace('.', ""), "0".repeat(36)),
+        prev_block_hash: None,
+        block_hash: format!("b{}{}", version.replace('.', ""), "f".repeat(60)),
+        hash_version: 1,
+        manifest: Vec::new(),
+        migrated_from: Vec::new(),
+        provenance: Default::default(),
+        entrypoint: "bin".to_string(),
+    }
+}
+
+#[test]
+fn test_format_date() {
+    use crate::core::cmd::log::format_date;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    assert_eq!(format_date(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+    assert_eq!(
+        format_date(UNIX_EPOCH + Duration::from_secs(1764074693)),
+        "2025-11-25 12:44:53 UTC"
+    );
+    assert_eq!(
+        format_date(UNIX_EPOCH + Duration::from_secs(951782400)),
+        "2000-02-29 00:00:00 UTC"
+    );
+}
+
+#[test]
+fn test_select_range_newest_first() {
+    use crate::core::cmd::log::select_range;
+
+    let blocks = vec![block("0.1.0", 0), block("0.2.0", 1), block("0.3.0", 2)];
+    let versions = |r: Option<&str>| -> Vec<String> {
+        select_range(&blocks, r)
+            .unwrap()
+            .iter()
+            .map(|b| b.version.clone())
+            .collect()
+    };
+
+    assert_eq!(versions(None), ["0.3.0", "0.2.0", "0.1.0"]);
+    assert_eq!(versions(Some("0.2.0..")), ["0.3.0", "0.2.0"]);
+    assert_eq!(versions(Some("..0.2.0")), ["0.2.0", "0.1.0"]);
+    assert_eq!(versions(Some("0.2.0")), ["0.2.0"]);
+    assert!(select_range(&blocks, Some("0.3.0..0.1.0")).is_err());
+    assert!(select_range(&blocks, Some("9.9.9")).is_err());
+
+    // an empty chain has nothing to show, but is not an error
+    assert!(select_range(&[], None).unwrap().is_empty());
+    assert!(select_range(&[], Some("0.1.0")).is_err());
+}
+
+#[test]
+fn test_render_log_oneline() {
+    use crate::core::cmd::log::render_log;
+
+    let blocks = [block("0.2.0", 86_400), block("0.1.0", 0)];
+    let refs: Vec<_> = blocks.iter().collect();
+
+    assert_eq!(
+        render_log(&refs, true),
+        "b020ffff 0.2.0 c0200000 1970-01-02\nb010ffff 0.1.0 c0100000 1970-01-01\n"
+    );
+}
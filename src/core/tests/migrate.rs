@@ -0,0 +1,72 @@
+#[test]
+fn test_migrate_legacy_chain() {
+    use crate::store::{meta::HASH_VERSION, mock::MockStore, traits::Store};
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    std::fs::write(&fake_bin, b"world").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+
+    // rewrite the chain as an older anvil would have produced it
+    let mut prev = None;
+    for block in anvil.blocks.iter_mut() {
+        let bytes = anvil.store.get_artifact(&block.artefact_hash).unwrap();
+        anvil.store.remove_block(&block.block_hash).unwrap();
+        block.hash_version = 0;
+        block.prev_block_hash = prev;
+        block.block_hash = MockStore::compute_block_hash(block);
+        anvil.store.add_artifact(&bytes, block).unwrap();
+        prev = Some(block.block_hash.clone());
+    }
+    anvil.save_blocks().unwrap();
+    assert!(anvil.validate_chain().is_ok());
+    let legacy_head = anvil.blocks[1].block_hash.clone();
+
+    assert_eq!(anvil.migrate_chain().unwrap(), 2);
+
+    assert!(anvil.blocks.iter().all(|b| b.hash_version == HASH_VERSION));
+    assert_ne!(anvil.blocks[1].block_hash, legacy_head);
+    assert!(anvil.verify_problems().is_empty());
+    assert_eq!(anvil.store.block_artifact(&legacy_head).unwrap(), None);
+    assert_eq!(
+        anvil
+            .store
+            .block_artifact(&anvil.blocks[1].block_hash)
+            .unwrap(),
+        Some(anvil.blocks[1].artefact_hash.clone())
+    );
+
+    // installs trusting the legacy head accept the migrated chain
+    assert_eq!(anvil.blocks[1].migrated_from, [legacy_head.as_str()]);
+    type Core = crate::core::AnvilCore<MockStore>;
+    assert!(Core::check_remote_chain(&anvil.blocks, Some(&legacy_head)).is_ok());
+    assert!(Core::check_remote_chain(&anvil.blocks, Some("rewritten")).is_err());
+
+    // the mapping is covered by the block hash
+    let mut forged = anvil.blocks.clone();
+    forged[1].migrated_from = vec!["rewritten".to_string()];
+    assert!(Core::check_remote_chain(&forged, Some("rewritten")).is_err());
+
+    // so is the claim that a different block used to be the trusted head
+    let mut forged = anvil.blocks.clone();
+    forged[1].artefact_hash = MockStore::compute_hash(b"malicious");
+    forged[1].block_hash = MockStore::compute_block_hash(&forged[1]);
+    assert_eq!(forged[1].migrated_from, [legacy_head.as_str()]);
+    assert!(Core::check_remote_chain(&forged, None).is_ok());
+    let err = Core::check_remote_chain(&forged, Some(&legacy_head)).unwrap_err();
+    assert!(err.to_string().contains("Possible tampering"));
+
+    // running it again is a no-op
+    assert_eq!(anvil.migrate_chain().unwrap(), 0);
+}
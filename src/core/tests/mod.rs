@@ -1,2 +1,107 @@
+pub mod artifacts;
 pub mod block_inc;
 pub mod corrupt_chain;
+pub mod gc;
+pub mod install;
+pub mod list;
+pub mod log;
+pub mod migrate;
+pub mod provenance;
+pub mod remote_chain;
+pub mod switch;
+pub mod sync;
+pub mod uninstall;
+pub mod update;
+pub mod verify;
+
+/// Run the test `name` again alone in a child process with `vars` set, for tests
+/// depending on process wide state such as `HOME` or `PATH`.
+///
+/// Returns `true` in the child, where the test body runs, and `false` in the
+/// parent once the child passed.
+#[cfg(test)]
+pub fn isolated(name: &str, vars: &[(&str, &std::path::Path)]) -> bool {
+    const CHILD: &str = "ANVIL_ISOLATED_TEST";
+    if std::env::var(CHILD).is_ok_and(|t| t == name) {
+        return true;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([name, "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD, name)
+        .envs(vars.iter().copied())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "{name} failed:\n{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    false
+}
+
+/// Git repository of a project `tool`, built from source by `printf built > bin`,
+/// packing its blocks into the store at `store`, which it lists in its `stores`.
+#[cfg(test)]
+pub struct RemoteProject {
+    pub dir: std::path::PathBuf,
+    pub store: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl RemoteProject {
+    pub fn new(root: &std::path::Path) -> Self {
+        let project = Self {
+            dir: root.join("tool"),
+            store: root.join("store"),
+        };
+        std::fs::create_dir_all(project.dir.join(".anvil")).unwrap();
+        std::fs::write(
+            project.dir.join(".anvil/anvil.yml"),
+            format!(
+                "project: {{ name: tool }}\n\
+                 build:\n  artifact_dir: .\n  entrypoint: bin\n  command: printf built > bin\n  incremental: false\n  jit: false\n\
+                 env: {{}}\nstores: [{}]\n",
+                project.store.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(project.dir.join(".gitignore"), "bin\n").unwrap();
+        project.git(&["init", "-q"]);
+        project.git(&["add", "-A"]);
+        project.git(&["commit", "-qm", "init"]);
+        project
+    }
+
+    pub fn git(&self, args: &[&str]) {
+        let output = std::process::Command::new("git")
+            .args(["-C", &self.dir.to_string_lossy()])
+            .args(["-c", "user.name=anvil", "-c", "user.email=anvil@localhost"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?}");
+    }
+
+    /// Pack `version` with `bin` holding `bytes`, without committing the chain.
+    pub fn pack(&self, version: &str, bytes: &[u8]) -> crate::store::meta::Meta {
+        let config = crate::config::Config::new(Some(&self.dir.join(".anvil/anvil.yml"))).unwrap();
+        let store = crate::store::fs_store::FsStore::new(&self.store, "tool").unwrap();
+        let mut anvil = crate::core::AnvilCore::new(Some(config), store, self.dir.clone()).unwrap();
+        anvil.config.build.command = "true".to_string();
+        std::fs::write(self.dir.join("bin"), bytes).unwrap();
+        anvil.pack(version, false, false).unwrap();
+        anvil.blocks.pop().unwrap()
+    }
+
+    /// Commit the chain, making the packed blocks visible to installs.
+    pub fn publish(&self) {
+        self.git(&["add", ".anvil/blocks.json"]);
+        self.git(&["commit", "-qm", "release"]);
+    }
+
+    pub fn url(&self) -> String {
+        self.dir.to_string_lossy().to_string()
+    }
+}
@@ -0,0 +1,203 @@
+#[test]
+fn test_pack_records_provenance() {
+    use crate::store::{mock::MockStore, traits::Store};
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+    anvil.config.build.env_whitelist = vec!["PATH".to_string(), "ANVIL_UNSET_VAR".to_string()];
+
+    std::fs::create_dir_all(temp.path().join(".anvil")).unwrap();
+    std::fs::write(temp.path().join(".anvil/anvil.yml"), "project: {}").unwrap();
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+
+    let p = &anvil.blocks[0].provenance;
+    assert_eq!(p.build_command.as_deref(), Some("echo"));
+    assert_eq!(p.config_hash, Some(MockStore::compute_hash(b"project: {}")));
+    assert_eq!(p.env.keys().collect::<Vec<_>>(), vec!["PATH"]);
+    assert_eq!(p.anvil_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    assert!(p.host.is_some());
+    // the project is not a git repository
+    assert_eq!(p.git_tree_hash, None);
+    assert_eq!(p.dirty, None);
+    assert!(p.source_hash.is_some());
+
+    // tampering with the provenance breaks the chain
+    assert!(anvil.validate_chain().is_ok());
+    anvil.blocks[0].provenance.build_command = Some("evil".to_string());
+    assert!(anvil.validate_chain().is_err());
+}
+
+#[test]
+fn test_pack_refuses_dirty_worktree() {
+    use crate::store::mock::MockStore;
+
+    let temp = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-C", &temp.path().to_string_lossy()])
+            .args(["-c", "user.name=anvil", "-c", "user.email=anvil@localhost"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?}");
+    };
+    git(&["init", "-q"]);
+    std::fs::write(temp.path().join(".gitignore"), "bin\n").unwrap();
+    std::fs::write(temp.path().join("main.c"), "int main;").unwrap();
+    git(&["add", "-A"]);
+    git(&["commit", "-qm", "init"]);
+
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let mut anvil = crate::core::AnvilCore::new(
+        Some(crate::config::Config::default()),
+        store,
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    anvil.config.build.entrypoint = temp.path().join("bin");
+    anvil.config.build.command = "echo".to_string();
+    std::fs::write(temp.path().join("bin"), b"hello").unwrap();
+
+    // ignored build outputs and the chain files do not make the tree dirty
+    anvil.pack("0.0.1", false, false).unwrap();
+    assert_eq!(anvil.blocks[0].provenance.dirty, Some(false));
+    assert!(anvil.blocks[0].provenance.git_tree_hash.is_some());
+    assert_eq!(anvil.blocks[0].provenance.source_hash, None);
+
+    std::fs::write(temp.path().join("main.c"), "int main();").unwrap();
+    std::fs::write(temp.path().join("extra.c"), "").unwrap();
+    std::fs::write(temp.path().join("bin"), b"world").unwrap();
+    let err = anvil.pack("0.0.2", false, false).unwrap_err().to_string();
+    assert!(err.contains("main.c") && err.contains("extra.c"), "{err}");
+    assert_eq!(anvil.blocks.len(), 1);
+
+    anvil.pack("0.0.2", false, true).unwrap();
+    assert_eq!(anvil.blocks[1].provenance.dirty, Some(true));
+}
+
+#[test]
+fn test_pack_outside_git() {
+    use crate::store::mock::MockStore;
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let mut anvil = crate::core::AnvilCore::new(
+        Some(crate::config::Config::default()),
+        store,
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    assert_eq!(anvil.current_commit, None);
+    anvil.config.build.entrypoint = "out/tool".into();
+    anvil.config.build.command = "echo".to_string();
+
+    std::fs::create_dir_all(temp.path().join("out")).unwrap();
+    std::fs::write(temp.path().join("main.c"), "int main;").unwrap();
+    std::fs::write(temp.path().join("out/tool"), b"v1").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    assert_eq!(anvil.blocks[0].git_commit, "");
+    let first = anvil.blocks[0].provenance.source_hash.clone().unwrap();
+
+    // build outputs are not sources
+    std::fs::write(temp.path().join("out/tool"), b"v2").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+    assert_eq!(
+        anvil.blocks[1].provenance.source_hash.as_ref(),
+        Some(&first)
+    );
+
+    std::fs::write(temp.path().join("main.c"), "int main();").unwrap();
+    std::fs::write(temp.path().join("out/tool"), b"v3").unwrap();
+    anvil.pack("0.0.3", false, false).unwrap();
+    assert_ne!(
+        anvil.blocks[2].provenance.source_hash.as_ref(),
+        Some(&first)
+    );
+    assert!(anvil.validate_chain().is_ok());
+
+    // tags need git
+    std::fs::write(temp.path().join("out/tool"), b"v4").unwrap();
+    let err = anvil.pack("0.0.4", true, false).unwrap_err().to_string();
+    assert!(err.contains("not a git repository"), "{err}");
+    assert_eq!(anvil.blocks.len(), 3);
+}
+
+#[test]
+fn test_pack_before_first_commit() {
+    use crate::store::mock::MockStore;
+
+    let temp = tempfile::tempdir().unwrap();
+    let status = std::process::Command::new("git")
+        .args(["-C", &temp.path().to_string_lossy(), "init", "-q"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let mut anvil = crate::core::AnvilCore::new(
+        Some(crate::config::Config::default()),
+        store,
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    anvil.config.build.entrypoint = temp.path().join("bin");
+    anvil.config.build.command = "echo".to_string();
+    std::fs::write(temp.path().join("bin"), b"hello").unwrap();
+
+    let err = anvil.pack("0.0.1", false, true).unwrap_err().to_string();
+    assert!(err.contains("no commit yet"), "{err}");
+    assert!(anvil.blocks.is_empty());
+}
+
+#[test]
+fn test_pack_without_git_installed() {
+    use crate::store::mock::MockStore;
+
+    // only a shell to run the build command
+    let path = tempfile::tempdir().unwrap();
+    std::os::unix::fs::symlink("/bin/sh", path.path().join("sh")).unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::provenance::test_pack_without_git_installed",
+        &[("PATH", path.path())],
+    ) {
+        return;
+    }
+
+    let pack = |root: &std::path::Path| {
+        std::fs::write(root.join("bin"), b"hello").unwrap();
+        let store = MockStore::new(root.to_string_lossy().to_string());
+        let mut anvil = crate::core::AnvilCore::new(
+            Some(crate::config::Config::default()),
+            store,
+            root.to_path_buf(),
+        )
+        .unwrap();
+        anvil.config.build.entrypoint = "bin".into();
+        anvil.config.build.command = "true".to_string();
+        anvil.pack("0.0.1", false, false).map(|_| anvil.blocks)
+    };
+
+    // a checkout is not mistaken for a project outside git
+    let checkout = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(checkout.path().join(".git")).unwrap();
+    std::fs::create_dir_all(checkout.path().join("sub")).unwrap();
+    for root in [checkout.path(), &checkout.path().join("sub")] {
+        let err = pack(root).unwrap_err().to_string();
+        assert!(err.contains("git is required to pack"), "{err}");
+    }
+
+    // projects outside git do not need it
+    let plain = tempfile::tempdir().unwrap();
+    let blocks = pack(plain.path()).unwrap();
+    assert!(blocks[0].provenance.source_hash.is_some());
+}
@@ -0,0 +1,41 @@
+#[test]
+fn test_check_remote_chain() {
+    use crate::{core::AnvilCore, store::mock::MockStore, store::traits::Store};
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil = AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    std::fs::write(&fake_bin, b"world").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+
+    let trusted = anvil.blocks[0].block_hash.clone();
+    assert!(AnvilCore::<MockStore>::check_remote_chain(&anvil.blocks, None).is_ok());
+    assert!(AnvilCore::<MockStore>::check_remote_chain(&anvil.blocks, Some(&trusted)).is_ok());
+
+    // a broken chain is refused
+    let mut broken = anvil.blocks.clone();
+    broken[1].git_commit = "deadbeef".into();
+    assert!(AnvilCore::<MockStore>::check_remote_chain(&broken, None).is_err());
+
+    // a consistent chain with rewritten history is flagged against the trusted head
+    let mut rewritten = anvil.blocks.clone();
+    rewritten[0].git_commit = "deadbeef".into();
+    let mut prev = None;
+    for block in rewritten.iter_mut() {
+        block.prev_block_hash = prev;
+        block.block_hash = MockStore::compute_block_hash(block);
+        prev = Some(block.block_hash.clone());
+    }
+    assert!(AnvilCore::<MockStore>::check_remote_chain(&rewritten, None).is_ok());
+    let err = AnvilCore::<MockStore>::check_remote_chain(&rewritten, Some(&trusted)).unwrap_err();
+    assert!(err.to_string().contains("tampering"));
+}
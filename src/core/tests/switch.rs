@@ -0,0 +1,46 @@
+#[test]
+fn test_replace_executable_swaps_binary() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let dest = temp.path().join("bin").join("tool");
+
+    crate::core::cmd::replace_executable(&dest, b"v1").unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"v1");
+
+    crate::core::cmd::replace_executable(&dest, b"v2").unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"v2");
+
+    let mode = std::fs::metadata(&dest).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+
+    // no temp file left behind next to the binary
+    assert_eq!(
+        std::fs::read_dir(dest.parent().unwrap()).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn test_replace_symlink_repoints_link() {
+    let temp = tempfile::tempdir().unwrap();
+    let v1 = temp.path().join("versions/aaa/tool");
+    let v2 = temp.path().join("versions/bbb/tool");
+    for (path, content) in [(&v1, b"v1"), (&v2, b"v2")] {
+        crate::core::cmd::replace_executable(path, content).unwrap();
+    }
+
+    let link = temp.path().join("bin/tool");
+    // a plain binary left by an older anvil is replaced as well
+    crate::core::cmd::replace_executable(&link, b"legacy").unwrap();
+
+    crate::core::cmd::replace_symlink(&v1, &link).unwrap();
+    assert_eq!(std::fs::read_link(&link).unwrap(), v1);
+    assert_eq!(std::fs::read(&link).unwrap(), b"v1");
+
+    crate::core::cmd::replace_symlink(&v2, &link).unwrap();
+    assert_eq!(std::fs::read(&link).unwrap(), b"v2");
+
+    // the previous version is still on disk
+    assert_eq!(std::fs::read(&v1).unwrap(), b"v1");
+}
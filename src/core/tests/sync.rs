@@ -0,0 +1,61 @@
+#[test]
+fn test_push_and_pull_resume() {
+    use crate::store::{http_store::HttpStore, mock::MockStore, tests::http::serve, traits::Store};
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    for (i, content) in ["hello", "wonderful", "world"].iter().enumerate() {
+        std::fs::write(&fake_bin, content).unwrap();
+        anvil.pack(&format!("0.0.{i}"), false, false).unwrap();
+    }
+
+    let (url, state) = serve();
+    let remote = HttpStore::new(&url, "mock");
+
+    // an interrupted push left only the first artefact behind
+    remote.add_artifact(b"hello", &anvil.blocks[0]).unwrap();
+    let report = anvil.push_to(&remote).unwrap();
+    assert_eq!(report.transferred, vec!["0.0.1", "0.0.2"]);
+    assert_eq!(report.skipped, 1);
+    assert_eq!(anvil.push_to(&remote).unwrap().transferred.len(), 0);
+
+    // a fresh clone pulls the chain, skipping what it already has
+    let clone = crate::core::AnvilCore::new(
+        None,
+        MockStore::new(temp.path().to_string_lossy().to_string()),
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    clone
+        .store
+        .add_artifact(b"world", &clone.blocks[2])
+        .unwrap();
+    let report = clone.pull_from(&remote).unwrap();
+    assert_eq!(report.transferred, vec!["0.0.0", "0.0.1"]);
+    assert_eq!(report.skipped, 1);
+    assert!(clone.verify_problems().is_empty());
+
+    // tampered remote objects are refused
+    let tampered = crate::core::AnvilCore::new(
+        None,
+        MockStore::new(temp.path().to_string_lossy().to_string()),
+        temp.path().to_path_buf(),
+    )
+    .unwrap();
+    state
+        .lock()
+        .unwrap()
+        .objects
+        .insert(anvil.blocks[0].artefact_hash.clone(), b"evil".to_vec());
+    assert!(tampered.pull_from(&remote).is_err());
+    assert!(!tampered.store.exists(&anvil.blocks[0].artefact_hash));
+}
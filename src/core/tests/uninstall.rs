@@ -0,0 +1,63 @@
+#[test]
+fn test_uninstall_keeps_packed_artefacts() {
+    use crate::{
+        core::{AnvilCore, cmd::install::InstallMeta},
+        store::{fs_store::FsStore, traits::Store},
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::uninstall::test_uninstall_keeps_packed_artefacts",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let store_dir = temp.path().join("store");
+    let project = temp.path().join("tool");
+    std::fs::create_dir_all(&project).unwrap();
+
+    // pack a block of `tool` in its own folder
+    let store = FsStore::new(&store_dir, "tool").unwrap();
+    let mut packer = AnvilCore::new(
+        Some(crate::config::Config::default()),
+        store,
+        project.clone(),
+    )
+    .unwrap();
+    packer.config.build.entrypoint = project.join("bin");
+    packer.config.build.command = "echo".to_string();
+    std::fs::write(project.join("bin"), b"packed").unwrap();
+    packer.pack("0.1.0", false, false).unwrap();
+    let packed = packer.blocks[0].artefact_hash.clone();
+
+    // an install of `tool` fetched another object, and recorded the packed one too
+    let store = FsStore::new(&store_dir, "tool").unwrap();
+    let fetched = FsStore::compute_hash(b"fetched");
+    store.put_object(&fetched, b"fetched").unwrap();
+    InstallMeta {
+        repo_url: "https://example.com/tool.git".to_string(),
+        local_repo_path: temp.path().join("repo"),
+        current_version: "0.1.0".to_string(),
+        current_commit: String::new(),
+        current_block_hash: packer.blocks[0].block_hash.clone(),
+        trusted_head: None,
+        cached_objects: vec![fetched.clone(), packed.clone()],
+    }
+    .save("tool")
+    .unwrap();
+
+    // uninstall from another folder, which is left alone
+    let elsewhere = temp.path().join("elsewhere");
+    std::fs::create_dir_all(&elsewhere).unwrap();
+    std::env::set_current_dir(&elsewhere).unwrap();
+    let mut anvil = AnvilCore::without_project(store).unwrap();
+    anvil.uninstall("tool", false).unwrap();
+    assert_eq!(std::fs::read_dir(&elsewhere).unwrap().count(), 0);
+
+    assert!(!anvil.store.exists(&fetched));
+    assert!(anvil.store.exists(&packed));
+    assert_eq!(anvil.store.list_blocks().unwrap().len(), 1);
+    assert!(packer.verify_problems().is_empty());
+}
@@ -0,0 +1,90 @@
+#[test]
+fn test_update_repack_at_same_commit() {
+    use crate::{
+        core::{AnvilCore, cmd::install::InstallMeta, tests::RemoteProject},
+        store::fs_store::FsStore,
+    };
+
+    let home = tempfile::tempdir().unwrap();
+    if !crate::core::tests::isolated(
+        "core::tests::update::test_update_repack_at_same_commit",
+        &[("HOME", home.path())],
+    ) {
+        return;
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let remote = RemoteProject::new(temp.path());
+    let first = remote.pack("0.1.0", b"v1");
+    let second = remote.pack("0.2.0", b"v2");
+    assert_eq!(first.git_commit, second.git_commit);
+    remote.publish();
+
+    let open_store = |name: &str| FsStore::new(&remote.store, name);
+    let mut anvil = AnvilCore::without_project(open_store("tool").unwrap()).unwrap();
+    anvil
+        .install(
+            &remote.url(),
+            Some("0.1.0".to_string()),
+            false,
+            false,
+            open_store,
+        )
+        .unwrap();
+
+    let err = AnvilCore::update(Some("tool"), true, false, open_store)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(err, "1 update(s) available");
+    assert_eq!(InstallMeta::load("tool").unwrap().current_version, "0.1.0");
+
+    AnvilCore::update(None, false, false, open_store).unwrap();
+    let installed = InstallMeta::load("tool").unwrap();
+    assert_eq!(installed.current_version, "0.2.0");
+    assert_eq!(installed.current_block_hash, second.block_hash);
+    let bin = crate::core::cmd::anvil_home().unwrap().join("bin/tool");
+    assert_eq!(std::fs::read(bin).unwrap(), b"v2");
+
+    AnvilCore::update(Some("tool"), true, false, open_store).unwrap();
+
+    // switching back is not a no-op either
+    anvil.switch("tool", "0.1.0", false).unwrap();
+    assert_eq!(InstallMeta::load("tool").unwrap().current_version, "0.1.0");
+}
+
+#[test]
+fn test_update_without_block_hash_compares_commits() {
+    use crate::core::cmd::install::InstallMeta;
+
+    let block: crate::store::meta::Meta = serde_json::from_value(serde_json::json!({
+        "artefact_hash": "a",
+        "artefact_type": "Bin",
+        "created_at": { "secs_since_epoch": 0, "nanos_since_epoch": 0 },
+        "version": "0.2.0",
+        "git_commit": "c1",
+        "prev_block_hash": null,
+        "block_hash": "b2",
+        "entrypoint": "bin",
+    }))
+    .unwrap();
+
+    // recorded by an anvil predating block hashes in the meta file
+    let mut installed = InstallMeta {
+        repo_url: String::new(),
+        local_repo_path: Default::default(),
+        current_version: "0.2.0".to_string(),
+        current_commit: "c1".to_string(),
+        current_block_hash: String::new(),
+        trusted_head: None,
+        cached_objects: Vec::new(),
+    };
+    assert!(installed.is_current(&block));
+    installed.current_version = "0.1.0".to_string();
+    assert!(!installed.is_current(&block));
+
+    installed.current_block_hash = "b1".to_string();
+    installed.current_version = "0.2.0".to_string();
+    assert!(!installed.is_current(&block));
+    installed.current_block_hash = "b2".to_string();
+    assert!(installed.is_current(&block));
+}
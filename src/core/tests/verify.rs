@@ -0,0 +1,58 @@
+#[test]
+fn test_verify_packed_chain() {
+    let temp = tempfile::tempdir().unwrap();
+    let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    std::fs::write(&fake_bin, b"world").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+
+    assert!(anvil.validate_chain().is_ok());
+    assert!(anvil.verify_problems().is_empty());
+    assert!(anvil.verify().is_ok());
+}
+
+#[test]
+fn test_verify_reports_every_problem() {
+    use crate::store::traits::Store;
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = crate::store::mock::MockStore::new(temp.path().to_string_lossy().to_string());
+    let config = crate::config::Config::default();
+
+    let fake_bin = temp.path().join("bin");
+
+    let mut anvil =
+        crate::core::AnvilCore::new(Some(config), store, temp.path().to_path_buf()).unwrap();
+    anvil.config.build.entrypoint = fake_bin.clone();
+    anvil.config.build.command = "echo".to_string();
+
+    std::fs::write(&fake_bin, b"hello").unwrap();
+    anvil.pack("0.0.1", false, false).unwrap();
+    std::fs::write(&fake_bin, b"wonderful").unwrap();
+    anvil.pack("0.0.2", false, false).unwrap();
+    std::fs::write(&fake_bin, b"world").unwrap();
+    anvil.pack("0.0.3", false, false).unwrap();
+
+    // corrupt the artefact of block 0 and drop the one of block 2
+    anvil.store.corrupt(&anvil.blocks[0].artefact_hash, b"evil");
+    anvil
+        .store
+        .remove_block(&anvil.blocks[2].block_hash)
+        .unwrap();
+    // tamper with block 1 metadata
+    anvil.blocks[1].git_commit = "deadbeef".into();
+
+    let problems = anvil.verify_problems();
+    assert_eq!(problems.len(), 3, "{problems:#?}");
+    assert!(anvil.verify().is_err());
+}
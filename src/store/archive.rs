@@ -0,0 +1,134 @@
+//! Deterministic tar archives of a directory, the bytes of `Pack` artifacts, and
+//! content hashes of a directory.
+//!
+//! Entries are sorted by path and carry no timestamps or owners, and file modes are
+//! reduced to 0o755 or 0o644 depending on the executable bit, so packing the same
+//! tree twice gives the same artefact hash.
+
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use tar::{Archive, Builder, EntryType, Header};
+
+/// Archive the content of `dir`, with paths relative to it.
+pub fn pack_dir(dir: &Path) -> Result<Vec<u8>> {
+    if !dir.is_dir() {
+        anyhow::bail!("Cannot pack {}: not a directory", dir.display());
+    }
+
+    let mut entries = Vec::new();
+    collect(dir, Path::new(""), &[], &mut entries)?;
+    entries.sort();
+
+    let mut builder = Builder::new(Vec::new());
+    for relative in entries {
+        let path = dir.join(&relative);
+        let metadata = path.symlink_metadata()?;
+
+        let mut header = Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        if metadata.file_type().is_symlink() {
+            header.set_entry_type(EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_size(0);
+            builder.append_link(&mut header, &relative, std::fs::read_link(&path)?)?;
+        } else if metadata.is_dir() {
+            header.set_entry_type(EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder.append_data(&mut header, &relative, std::io::empty())?;
+        } else {
+            let executable = metadata.permissions().mode() & 0o111 != 0;
+            header.set_entry_type(EntryType::Regular);
+            header.set_mode(if executable { 0o755 } else { 0o644 });
+            header.set_size(metadata.len());
+            builder.append_data(&mut header, &relative, std::fs::File::open(&path)?)?;
+        }
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Paths under `root`, relative to it, leaving out `exclude` and everything below.
+fn collect(
+    root: &Path,
+    relative: &Path,
+    exclude: &[PathBuf],
+    entries: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if exclude.contains(&path) {
+            continue;
+        }
+        let is_dir = entry.file_type()?.is_dir();
+        entries.push(path.clone());
+        if is_dir {
+            collect(root, &path, exclude, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// sha256 over the paths, executable bits and contents of every file under `dir`,
+/// leaving out the `exclude` paths, relative to `dir`. Timestamps and owners are
+/// ignored like in [`pack_dir`].
+pub fn tree_hash(dir: &Path, exclude: &[PathBuf]) -> Result<String> {
+    let mut entries = Vec::new();
+    collect(dir, Path::new(""), exclude, &mut entries)?;
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    for relative in entries {
+        let path = dir.join(&relative);
+        let metadata = path.symlink_metadata()?;
+
+        hasher.update(relative.to_string_lossy().as_bytes());
+        if metadata.file_type().is_symlink() {
+            hasher.update(b"\0link\0");
+            hasher.update(std::fs::read_link(&path)?.to_string_lossy().as_bytes());
+        } else if metadata.is_dir() {
+            hasher.update(b"\0dir\0");
+        } else {
+            let executable = metadata.permissions().mode() & 0o111 != 0;
+            hasher.update(if executable { b"\0exec\0" } else { b"\0file\0" });
+            hasher.update(metadata.len().to_le_bytes());
+            std::io::copy(&mut std::fs::File::open(&path)?, &mut hasher)?;
+        }
+        hasher.update(b"\n");
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Extract an archive made by [`pack_dir`] into `dest`, keeping executable bits.
+///
+/// The archive is unpacked next to `dest` and renamed into place, so `dest`
+/// never holds a partial tree.
+pub fn unpack_dir(bytes: &[u8], dest: &Path) -> Result<()> {
+    let parent = dest
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid destination: {}", dest.display()))?;
+    std::fs::create_dir_all(parent)?;
+
+    let tmp = tempfile::Builder::new()
+        .prefix(".anvil-unpack")
+        .tempdir_in(parent)?;
+    let mut archive = Archive::new(bytes);
+    archive.set_preserve_mtime(false);
+    archive.unpack(tmp.path())?;
+
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)?;
+    }
+    let unpacked = tmp.keep();
+    std::fs::set_permissions(&unpacked, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(unpacked, dest)?;
+    Ok(())
+}
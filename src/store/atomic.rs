@@ -0,0 +1,33 @@
+use std::{fs, io::Write, path::Path};
+
+use anyhow::Result;
+
+/// Write `bytes` to `path` so that readers see either the old or the new content.
+///
+/// The data goes to a temporary file in the same directory, is fsynced and then
+/// renamed over `path`; the directory is synced too so the rename survives a crash.
+pub fn atomic_write(path: &Path, bytes: &[u8]) -> Result<()> {
+    atomic_write_with_permissions(path, bytes, None)
+}
+
+pub fn atomic_write_with_permissions(
+    path: &Path,
+    bytes: &[u8],
+    permissions: Option<fs::Permissions>,
+) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid path: {}", path.display()))?;
+    fs::create_dir_all(dir)?;
+
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(bytes)?;
+    if let Some(permissions) = permissions {
+        tmp.as_file().set_permissions(permissions)?;
+    }
+    tmp.as_file().sync_all()?;
+    tmp.persist(path)?;
+
+    fs::File::open(dir)?.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::io::Read;
+
+use anyhow::Result;
+
+use crate::config::{Codec, Compression};
+
+/// Magic prefix of stored objects, followed by one byte naming the codec.
+///
+/// Objects written before compression existed have no header and are read raw.
+const MAGIC: &[u8; 4] = b"ANVO";
+
+impl Codec {
+    fn id(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Zstd => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Zstd),
+            _ => anyhow::bail!("Unknown object codec {id}"),
+        }
+    }
+}
+
+/// Object bytes as written to disk: header followed by the (compressed) payload.
+pub fn encode(bytes: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(MAGIC.len() + 1 + bytes.len());
+    out.extend_from_slice(MAGIC);
+    out.push(compression.codec.id());
+    match compression.codec {
+        Codec::None => out.extend_from_slice(bytes),
+        Codec::Zstd => out.extend(zstd::encode_all(bytes, compression.level)?),
+    }
+    Ok(out)
+}
+
+pub fn decode(data: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(payload) = data.strip_prefix(MAGIC) else {
+        return Ok(data);
+    };
+    let (&id, payload) = payload
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("Truncated object header"))?;
+    match Codec::from_id(id)? {
+        Codec::None => Ok(payload.to_vec()),
+        Codec::Zstd => Ok(zstd::decode_all(payload)?),
+    }
+}
+
+/// First byte of the object read from `reader`, only decoding what it takes to get it.
+pub fn first_byte(mut reader: impl Read) -> Result<Option<u8>> {
+    let mut header = Vec::with_capacity(MAGIC.len() + 1);
+    reader
+        .by_ref()
+        .take(MAGIC.len() as u64 + 1)
+        .read_to_end(&mut header)?;
+    let Some(payload) = header.strip_prefix(MAGIC) else {
+        return Ok(header.first().copied());
+    };
+    let &[id] = payload else {
+        anyhow::bail!("Truncated object header");
+    };
+    let mut byte = Vec::with_capacity(1);
+    match Codec::from_id(id)? {
+        Codec::None => reader.take(1).read_to_end(&mut byte)?,
+        Codec::Zstd => zstd::Decoder::new(reader)?.take(1).read_to_end(&mut byte)?,
+    };
+    Ok(byte.first().copied())
+}
@@ -1,11 +1,23 @@
 use std::{
+    collections::BTreeMap,
     env, fs,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
 };
 
 use anyhow::Result;
 
-use crate::store::{meta::Meta, traits::Store};
+use crate::{
+    config::Compression,
+    store::{
+        IndexEntry,
+        atomic::atomic_write,
+        codec,
+        lock::{FileLock, StoreLock},
+        meta::{Manifest, Meta},
+        traits::Store,
+    },
+};
 
 #[derive(Debug)]
 pub enum StoreState {
@@ -13,20 +25,47 @@ pub enum StoreState {
     Incremental,
 }
 
+/// Content addressed store shared by every project.
+///
+/// Objects live under `objects/<2 first hex chars>/<rest of artefact hash>` and each
+/// project keeps an index in `index/<project>.json` mapping its block hashes to them.
+/// Objects are compressed on disk according to `compression`.
 #[derive(Debug)]
 pub struct FsStore {
     root: PathBuf,
+    project: String,
+    compression: Compression,
+    /// Lock of the store while some guard of this instance holds it.
+    held: Mutex<Weak<FileLock>>,
 }
 
+type BlockIndex = BTreeMap<String, String>;
+
 fn get_home_dir() -> Option<PathBuf> {
     env::var("HOME").ok().map(PathBuf::from)
 }
 
+/// Whether `dir` is a non empty store of the old layout: files named by block hashes only.
+fn is_project_store(dir: &Path) -> Result<bool> {
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let is_hash = name
+            .to_str()
+            .is_some_and(|n| n.len() == 64 && n.bytes().all(|b| b.is_ascii_hexdigit()));
+        if !is_hash || !entry.file_type()?.is_file() {
+            return Ok(false);
+        }
+        empty = false;
+    }
+    Ok(!empty)
+}
+
 impl FsStore {
-    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+    pub fn new(path: impl AsRef<Path>, project: &str) -> Result<Self> {
         let home = get_home_dir().unwrap();
         let path = home.join(path);
-        println!("debug: path: {:#?}", path);
 
         if !path.exists() {
             std::fs::create_dir_all(&path)?;
@@ -35,33 +74,245 @@ impl FsStore {
         if !path.is_dir() {
             return Err(anyhow::anyhow!("Store path exists but is not a directory"));
         }
-        Ok(Self { root: path })
+
+        Ok(Self {
+            root: path,
+            project: project.to_string(),
+            compression: Compression::default(),
+            held: Mutex::new(Weak::new()),
+        })
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
     }
 
     pub fn get_path(path: &str) -> PathBuf {
         let home = get_home_dir().unwrap();
         home.join(path)
     }
+
+    pub fn object_path(&self, artefact_hash: &str) -> PathBuf {
+        let (shard, rest) = artefact_hash.split_at(artefact_hash.len().min(2));
+        self.root.join("objects").join(shard).join(rest)
+    }
+
+    fn index_path(&self, project: &str) -> PathBuf {
+        self.root.join("index").join(format!("{project}.json"))
+    }
+
+    fn read_index(&self, project: &str) -> Result<BlockIndex> {
+        let path = self.index_path(project);
+        if !path.exists() {
+            return Ok(BlockIndex::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn write_index(&self, project: &str, index: &BlockIndex) -> Result<()> {
+        let path = self.index_path(project);
+        atomic_write(&path, serde_json::to_string_pretty(index)?.as_bytes())
+    }
+
+    /// Artefact hashes referenced by any project index.
+    fn referenced_objects(&self) -> Result<Vec<String>> {
+        let dir = self.root.join("index");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut hashes = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some(project) = path.file_stem().and_then(|p| p.to_str()) {
+                hashes.extend(self.read_index(project)?.into_values());
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Move stores from the old `<root>/<project>/<block_hash>` layout into the
+    /// shared object directory, keeping their blocks in the project index.
+    ///
+    /// Only directories holding nothing but files named by block hashes are
+    /// migrated, and each is removed once it is empty.
+    pub fn migrate_project_stores(&self) -> Result<()> {
+        let _lock = self.lock()?;
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.file_type()?.is_dir()
+                || name == "objects"
+                || name == "index"
+                || !is_project_store(&entry.path())?
+            {
+                continue;
+            }
+
+            let mut index = self.read_index(&name)?;
+            for object in fs::read_dir(entry.path())? {
+                let object = object?;
+                let bytes = fs::read(object.path())?;
+                let artefact_hash = Self::compute_hash(&bytes);
+                self.write_object(&artefact_hash, &bytes)?;
+                index.insert(
+                    object.file_name().to_string_lossy().to_string(),
+                    artefact_hash,
+                );
+                self.write_index(&name, &index)?;
+                fs::remove_file(object.path())?;
+            }
+            fs::remove_dir(entry.path())?;
+            println!("Migrated store of {name} to the shared object store");
+        }
+        Ok(())
+    }
+
+    fn write_object(&self, artefact_hash: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.object_path(artefact_hash);
+        if path.exists() {
+            return Ok(());
+        }
+        atomic_write(&path, &codec::encode(bytes, self.compression)?)
+    }
 }
 
 impl Store for FsStore {
     fn add_artifact(&self, artifact_byte: &[u8], meta: &Meta) -> Result<()> {
-        let path = self.root.join(&meta.block_hash);
-        fs::write(path, artifact_byte)?;
-        Ok(())
+        Self::check_hash(&meta.artefact_hash, artifact_byte)?;
+        let _lock = self.lock()?;
+        self.write_object(&meta.artefact_hash, artifact_byte)?;
+
+        let mut index = self.read_index(&self.project)?;
+        index.insert(meta.block_hash.clone(), meta.artefact_hash.clone());
+        self.write_index(&self.project, &index)
     }
 
-    fn get_artifact(&self, hash: &str) -> Result<Vec<u8>> {
-        let path = self.root.join(hash);
-        let data = fs::read(path)?;
+    fn get_artifact(&self, artefact_hash: &str) -> Result<Vec<u8>> {
+        let data = codec::decode(fs::read(self.object_path(artefact_hash))?)?;
+        Self::check_hash(artefact_hash, &data)?;
         Ok(data)
     }
 
-    fn exists(&self, hash: &str) -> bool {
-        self.root.join(hash).exists()
+    /// Binaries are told apart by their first byte, without reading them whole.
+    fn get_manifest(&self, artefact_hash: &str) -> Result<Option<Manifest>> {
+        let file = fs::File::open(self.object_path(artefact_hash))?;
+        if codec::first_byte(file)? != Some(b'{') {
+            return Ok(None);
+        }
+        Ok(Manifest::parse(&self.get_artifact(artefact_hash)?))
+    }
+
+    fn exists(&self, artefact_hash: &str) -> bool {
+        self.object_path(artefact_hash).exists()
+    }
+
+    fn block_artifact(&self, block_hash: &str) -> Result<Option<String>> {
+        Ok(self.read_index(&self.project)?.remove(block_hash))
+    }
+
+    fn put_object(&self, artefact_hash: &str, bytes: &[u8]) -> Result<()> {
+        Self::check_hash(artefact_hash, bytes)?;
+        let _lock = self.lock()?;
+        self.write_object(artefact_hash, bytes)
+    }
+
+    fn remove_block(&self, block_hash: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut index = self.read_index(&self.project)?;
+        let Some(artefact_hash) = index.remove(block_hash) else {
+            return Ok(());
+        };
+        self.write_index(&self.project, &index)?;
+
+        // other blocks, possibly of other projects, may share the same bytes
+        if !self.referenced_objects()?.contains(&artefact_hash) {
+            let path = self.object_path(&artefact_hash);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn list_blocks(&self) -> Result<Vec<String>> {
+        Ok(self.read_index(&self.project)?.into_keys().collect())
+    }
+
+    fn index_entries(&self) -> Result<Vec<IndexEntry>> {
+        let dir = self.root.join("index");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(project) = path.file_stem().and_then(|p| p.to_str()) else {
+                continue;
+            };
+            for (block_hash, artefact_hash) in self.read_index(project)? {
+                entries.push(IndexEntry {
+                    project: project.to_string(),
+                    block_hash,
+                    artefact_hash,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    fn remove_index_entry(&self, entry: &IndexEntry) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut index = self.read_index(&entry.project)?;
+        if index.remove(&entry.block_hash).is_some() {
+            self.write_index(&entry.project, &index)?;
+        }
+        Ok(())
+    }
+
+    fn list_objects(&self) -> Result<Vec<(String, u64)>> {
+        let dir = self.root.join("objects");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut objects = Vec::new();
+        for shard in fs::read_dir(dir)? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            let prefix = shard.file_name().to_string_lossy().to_string();
+            for object in fs::read_dir(shard.path())? {
+                let object = object?;
+                let rest = object.file_name().to_string_lossy().to_string();
+                objects.push((format!("{prefix}{rest}"), object.metadata()?.len()));
+            }
+        }
+        objects.sort();
+        Ok(objects)
+    }
+
+    fn remove_object(&self, artefact_hash: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let path = self.object_path(artefact_hash);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
     }
 
     fn root(&self) -> &std::path::Path {
         &self.root
     }
+
+    /// Serializes writers of objects and indexes across processes.
+    fn lock(&self) -> Result<StoreLock> {
+        let mut held = self.held.lock().unwrap();
+        if let Some(lock) = held.upgrade() {
+            return Ok(StoreLock::new(lock));
+        }
+        let lock = Arc::new(FileLock::acquire(&self.root.join("store.lock"))?);
+        *held = Arc::downgrade(&lock);
+        Ok(StoreLock::new(lock))
+    }
 }
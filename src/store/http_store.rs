@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Result;
+
+use crate::store::{IndexEntry, meta::Meta, traits::Store};
+
+/// Remote store speaking a small REST protocol, to share artefacts from a cache server.
+///
+/// * `GET|PUT|HEAD|DELETE /objects/<artefact_hash>`: raw artefact bytes
+/// * `GET /objects`: JSON list of `[artefact_hash, size]`
+/// * `GET|PUT|DELETE /index/<project>/<block_hash>`: artefact hash of a block, as text
+/// * `GET /index`: JSON object `{ project: { block_hash: artefact_hash } }`
+///
+/// Downloaded objects are checked against their hash before being returned.
+#[derive(Debug)]
+pub struct HttpStore {
+    base_url: String,
+    project: String,
+    agent: ureq::Agent,
+}
+
+type Indexes = BTreeMap<String, BTreeMap<String, String>>;
+
+impl HttpStore {
+    pub fn new(base_url: &str, project: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            project: project.to_string(),
+            agent: ureq::Agent::new_with_defaults(),
+        }
+    }
+
+    fn object_url(&self, artefact_hash: &str) -> String {
+        format!("{}/objects/{artefact_hash}", self.base_url)
+    }
+
+    fn block_url(&self, project: &str, block_hash: &str) -> String {
+        format!("{}/index/{project}/{block_hash}", self.base_url)
+    }
+
+    fn get_bytes(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        match self.agent.get(url).call() {
+            Ok(response) => Ok(Some(
+                response
+                    .into_body()
+                    .into_with_config()
+                    .limit(u64::MAX)
+                    .read_to_vec()?,
+            )),
+            Err(ureq::Error::StatusCode(404)) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("GET {url} failed: {e}")),
+        }
+    }
+
+    fn delete(&self, url: &str) -> Result<()> {
+        match self.agent.delete(url).call() {
+            Ok(_) | Err(ureq::Error::StatusCode(404)) => Ok(()),
+            Err(e) => Err(anyhow::anyhow!("DELETE {url} failed: {e}")),
+        }
+    }
+
+    fn indexes(&self) -> Result<Indexes> {
+        let url = format!("{}/index", self.base_url);
+        match self.get_bytes(&url)? {
+            Some(body) => Ok(serde_json::from_slice(&body)?),
+            None => Ok(Indexes::new()),
+        }
+    }
+}
+
+impl Store for HttpStore {
+    fn add_artifact(&self, artifact_byte: &[u8], meta: &Meta) -> Result<()> {
+        if !self.exists(&meta.artefact_hash) {
+            self.put_object(&meta.artefact_hash, artifact_byte)?;
+        } else {
+            Self::check_hash(&meta.artefact_hash, artifact_byte)?;
+        }
+
+        let url = self.block_url(&self.project, &meta.block_hash);
+        self.agent
+            .put(&url)
+            .send(meta.artefact_hash.as_bytes())
+            .map_err(|e| anyhow::anyhow!("PUT {url} failed: {e}"))?;
+        Ok(())
+    }
+
+    fn get_artifact(&self, artefact_hash: &str) -> Result<Vec<u8>> {
+        let data = self
+            .get_bytes(&self.object_url(artefact_hash))?
+            .ok_or_else(|| anyhow::anyhow!("Artefact {artefact_hash} not found on remote"))?;
+        Self::check_hash(artefact_hash, &data)?;
+        Ok(data)
+    }
+
+    fn exists(&self, artefact_hash: &str) -> bool {
+        self.agent
+            .head(self.object_url(artefact_hash))
+            .call()
+            .is_ok()
+    }
+
+    fn block_artifact(&self, block_hash: &str) -> Result<Option<String>> {
+        let body = self.get_bytes(&self.block_url(&self.project, block_hash))?;
+        Ok(body.map(|b| String::from_utf8_lossy(&b).trim().to_string()))
+    }
+
+    fn put_object(&self, artefact_hash: &str, bytes: &[u8]) -> Result<()> {
+        Self::check_hash(artefact_hash, bytes)?;
+        let url = self.object_url(artefact_hash);
+        self.agent
+            .put(&url)
+            .send(bytes)
+            .map_err(|e| anyhow::anyhow!("PUT {url} failed: {e}"))?;
+        Ok(())
+    }
+
+    fn remove_block(&self, block_hash: &str) -> Result<()> {
+        let Some(artefact_hash) = self.block_artifact(block_hash)? else {
+            return Ok(());
+        };
+        self.delete(&self.block_url(&self.project, block_hash))?;
+
+        if !self
+            .index_entries()?
+            .iter()
+            .any(|e| e.artefact_hash == artefact_hash)
+        {
+            self.remove_object(&artefact_hash)?;
+        }
+        Ok(())
+    }
+
+    fn list_blocks(&self) -> Result<Vec<String>> {
+        Ok(self
+            .indexes()?
+            .remove(&self.project)
+            .unwrap_or_default()
+            .into_keys()
+            .collect())
+    }
+
+    fn index_entries(&self) -> Result<Vec<IndexEntry>> {
+        let mut entries = Vec::new();
+        for (project, index) in self.indexes()? {
+            for (block_hash, artefact_hash) in index {
+                entries.push(IndexEntry {
+                    project: project.clone(),
+                    block_hash,
+                    artefact_hash,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    fn remove_index_entry(&self, entry: &IndexEntry) -> Result<()> {
+        self.delete(&self.block_url(&entry.project, &entry.block_hash))
+    }
+
+    fn list_objects(&self) -> Result<Vec<(String, u64)>> {
+        let url = format!("{}/objects", self.base_url);
+        match self.get_bytes(&url)? {
+            Some(body) => Ok(serde_json::from_slice(&body)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn remove_object(&self, artefact_hash: &str) -> Result<()> {
+        self.delete(&self.object_url(artefact_hash))
+    }
+
+    fn root(&self) -> &Path {
+        Path::new(&self.base_url)
+    }
+}
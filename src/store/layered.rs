@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::store::{
+    IndexEntry,
+    fs_store::FsStore,
+    http_store::HttpStore,
+    lock::StoreLock,
+    meta::{Manifest, Meta},
+    traits::Store,
+};
+
+/// A local store in front of remote ones.
+///
+/// Reads fall through to the remotes in order, and objects found remotely are
+/// cached locally. Everything else, writes included, only touches the local store:
+/// remotes are filled with `anvil push`.
+pub struct LayeredStore<L: Store> {
+    local: L,
+    remotes: Vec<Box<dyn Store>>,
+}
+
+/// Whether `location` is the url of an [`HttpStore`] rather than a directory.
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Open the store at `location`: an `http(s)://` url or a directory.
+pub fn open_store(location: &str, project: &str) -> Result<Box<dyn Store>> {
+    if is_url(location) {
+        Ok(Box::new(HttpStore::new(location, project)))
+    } else {
+        Ok(Box::new(FsStore::new(location, project)?))
+    }
+}
+
+impl<L: Store> LayeredStore<L> {
+    pub fn new(local: L) -> Self {
+        Self {
+            local,
+            remotes: Vec::new(),
+        }
+    }
+
+    pub fn with_remote(mut self, remote: Box<dyn Store>) -> Self {
+        self.remotes.push(remote);
+        self
+    }
+
+    pub fn local(&self) -> &L {
+        &self.local
+    }
+}
+
+impl<L: Store> Store for LayeredStore<L> {
+    fn add_artifact(&self, artifact_byte: &[u8], meta: &Meta) -> Result<()> {
+        self.local.add_artifact(artifact_byte, meta)
+    }
+
+    fn get_artifact(&self, artefact_hash: &str) -> Result<Vec<u8>> {
+        if self.local.exists(artefact_hash) {
+            return self.local.get_artifact(artefact_hash);
+        }
+
+        let mut last_error = None;
+        for remote in &self.remotes {
+            if !remote.exists(artefact_hash) {
+                continue;
+            }
+            // remotes check the bytes against the hash before handing them out
+            match remote.get_artifact(artefact_hash) {
+                Ok(bytes) => {
+                    self.local.put_object(artefact_hash, &bytes)?;
+                    return Ok(bytes);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| anyhow::anyhow!("Artefact {artefact_hash} not found in any store")))
+    }
+
+    fn get_manifest(&self, artefact_hash: &str) -> Result<Option<Manifest>> {
+        if self.local.exists(artefact_hash) {
+            return self.local.get_manifest(artefact_hash);
+        }
+        Ok(Manifest::parse(&self.get_artifact(artefact_hash)?))
+    }
+
+    fn exists(&self, artefact_hash: &str) -> bool {
+        self.local.exists(artefact_hash) || self.remotes.iter().any(|r| r.exists(artefact_hash))
+    }
+
+    fn exists_locally(&self, artefact_hash: &str) -> bool {
+        self.local.exists(artefact_hash)
+    }
+
+    fn block_artifact(&self, block_hash: &str) -> Result<Option<String>> {
+        if let Some(hash) = self.local.block_artifact(block_hash)? {
+            return Ok(Some(hash));
+        }
+        for remote in &self.remotes {
+            if let Some(hash) = remote.block_artifact(block_hash)? {
+                return Ok(Some(hash));
+            }
+        }
+        Ok(None)
+    }
+
+    fn put_object(&self, artefact_hash: &str, bytes: &[u8]) -> Result<()> {
+        self.local.put_object(artefact_hash, bytes)
+    }
+
+    fn remove_block(&self, block_hash: &str) -> Result<()> {
+        self.local.remove_block(block_hash)
+    }
+
+    fn list_blocks(&self) -> Result<Vec<String>> {
+        self.local.list_blocks()
+    }
+
+    fn index_entries(&self) -> Result<Vec<IndexEntry>> {
+        self.local.index_entries()
+    }
+
+    fn remove_index_entry(&self, entry: &IndexEntry) -> Result<()> {
+        self.local.remove_index_entry(entry)
+    }
+
+    fn list_objects(&self) -> Result<Vec<(String, u64)>> {
+        self.local.list_objects()
+    }
+
+    fn remove_object(&self, artefact_hash: &str) -> Result<()> {
+        self.local.remove_object(artefact_hash)
+    }
+
+    fn root(&self) -> &Path {
+        self.local.root()
+    }
+
+    fn lock(&self) -> Result<StoreLock> {
+        self.local.lock()
+    }
+}
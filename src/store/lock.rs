@@ -0,0 +1,95 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+static LOCK_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// How long to wait for a lock before giving up, forever when never set.
+pub fn set_lock_timeout(timeout: Duration) {
+    let _ = LOCK_TIMEOUT.set(timeout);
+}
+
+/// Advisory exclusive lock on a file, released when dropped.
+///
+/// The holder writes its pid in the file so that waiting processes can tell who
+/// they are waiting for.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    pub fn acquire(path: &Path) -> Result<Self> {
+        Self::acquire_with_timeout(path, LOCK_TIMEOUT.get().copied())
+    }
+
+    pub fn acquire_with_timeout(path: &Path, timeout: Option<Duration>) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let start = Instant::now();
+        let mut announced = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    let holder = Self::holder(&mut file);
+                    if timeout.is_some_and(|t| start.elapsed() >= t) {
+                        anyhow::bail!(
+                            "Timed out waiting for lock {} held by pid {holder}",
+                            path.display()
+                        );
+                    }
+                    if !announced {
+                        eprintln!("Waiting for lock {} held by pid {holder}", path.display());
+                        announced = true;
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+
+        Ok(Self { _file: file })
+    }
+
+    fn holder(file: &mut File) -> String {
+        let mut pid = String::new();
+        let _ = file.rewind().and_then(|_| file.read_to_string(&mut pid));
+        match pid.trim() {
+            "" => "unknown".to_string(),
+            pid => pid.to_string(),
+        }
+    }
+}
+
+/// Guard of [`crate::store::traits::Store::lock`], the store stays locked until
+/// every guard taken on it is dropped. Empty for stores without locking.
+#[derive(Debug, Clone, Default)]
+pub struct StoreLock {
+    _lock: Option<Arc<FileLock>>,
+}
+
+impl StoreLock {
+    pub fn new(lock: Arc<FileLock>) -> Self {
+        Self { _lock: Some(lock) }
+    }
+}
@@ -1,12 +1,30 @@
-use std::{process::Command, time::SystemTime};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+    process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Block hash encoding produced by this build of anvil, see [`Meta::hashed_bytes`].
+pub const HASH_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub enum ArtefactType {
+    #[default]
+    #[serde(alias = "bin")]
     Bin,
+    #[serde(alias = "int")]
     Int,
+    /// Directory archived with [`crate::store::archive::pack_dir`].
+    #[serde(alias = "pack")]
     Pack,
+    /// Installed next to the binaries of the block, without being linked.
+    #[serde(alias = "lib")]
+    Lib,
+    /// [`Manifest`] of a multi-artifact block.
+    Manifest,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -16,7 +34,7 @@ pub struct Dependency {
     hash: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Meta {
     pub artefact_hash: String,
     pub artefact_type: ArtefactType,
@@ -24,20 +42,340 @@ pub struct Meta {
     pub version: String,
 
     pub git_commit: String,
-    // pub git_tree_hash: String,
     // pub version: Option<String>,
 
     // pub build_inputs: Vec<Dependency>,
-    // pub build_command: String,
-    // pub config_hash: String,
+    /// How the artefact was produced, covered by `block_hash`.
+    #[serde(flatten)]
+    pub provenance: Provenance,
     pub prev_block_hash: Option<String>,
     pub block_hash: String,
+    /// Encoding used for `block_hash`, 0 for chains packed before it was versioned.
+    #[serde(default)]
+    pub hash_version: u32,
     pub entrypoint: String,
-    // pub env: HashMap<String, String>,
+    /// Artifacts of a multi-artifact block, `artefact_hash` is then the hash of its
+    /// [`Manifest`]. Empty for blocks holding the single `entrypoint` artefact.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub manifest: Vec<ManifestEntry>,
+    /// Hashes the block had before `anvil migrate` rehashed it, oldest first, so
+    /// installs trusting one of them still recognize the migrated chain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub migrated_from: Vec<String>,
+}
+
+/// Build context recorded by `pack`.
+///
+/// Fields are unset for blocks packed before they were recorded, and when the
+/// information is not available, such as the git fields outside a repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Provenance {
+    /// Tree of `git_commit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_tree_hash: Option<String>,
+    /// Whether the worktree had changes not in `git_commit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirty: Option<bool>,
+    /// Content hash of the project sources, recorded instead of the git fields
+    /// for projects outside git, see [`crate::store::archive::tree_hash`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// sha256 of `anvil.yml`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Values of the variables of `build.env_whitelist` that were set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Target triple of the anvil that packed the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anvil_version: Option<String>,
+}
+
+/// One named artifact of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub path: String,
+    pub artefact_type: ArtefactType,
+    pub artefact_hash: String,
+    /// Executable inside a `Pack` archive, relative to it, linked as `name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Refuse names and paths escaping the directories they are joined to when the
+    /// artifact is built, installed and linked, as they may come from a remote chain.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let mut name = Path::new(&self.name).components();
+        if !matches!(
+            (name.next(), name.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            anyhow::bail!("Invalid artifact name {:?}: must be a file name", self.name);
+        }
+        if !is_contained(&self.path) {
+            anyhow::bail!(
+                "Invalid path {:?} of artifact {}: must be relative, without `..`",
+                self.path,
+                self.name
+            );
+        }
+        if let Some(entrypoint) = &self.entrypoint
+            && !is_contained(entrypoint)
+        {
+            anyhow::bail!(
+                "Invalid entrypoint {entrypoint:?} of artifact {}: must be relative, without `..`",
+                self.name
+            );
+        }
+        if self.artefact_type == ArtefactType::Manifest {
+            anyhow::bail!("Artifact {} cannot be a manifest", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Whether `path` stays inside the directory it is joined to.
+fn is_contained(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Object stored for a multi-artifact block, listing its artifacts.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub anvil_manifest: u32,
+    pub artifacts: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new(artifacts: &[ManifestEntry]) -> Self {
+        Self {
+            anvil_manifest: 1,
+            artifacts: artifacts.to_vec(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    /// The manifest stored in `bytes`, `None` for any other object.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.first() != Some(&b'{') {
+            return None;
+        }
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+/// `Meta` as it was serialized by the first anvil builds, hashed by `hash_version` 0.
+#[derive(Serialize)]
+struct LegacyMeta<'a> {
+    artefact_hash: &'a str,
+    artefact_type: &'a ArtefactType,
+    created_at: SystemTime,
+    version: &'a str,
+    git_commit: &'a str,
+    prev_block_hash: Option<&'a str>,
+    block_hash: &'a str,
+    entrypoint: &'a str,
+}
+
+impl ArtefactType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArtefactType::Bin => "Bin",
+            ArtefactType::Int => "Int",
+            ArtefactType::Pack => "Pack",
+            ArtefactType::Lib => "Lib",
+            ArtefactType::Manifest => "Manifest",
+        }
+    }
+}
+
+impl Meta {
+    /// Artifacts to install for this block: its manifest, or the entrypoint
+    /// installed as `project` for single artefact blocks.
+    pub fn artifacts(&self, project: &str) -> Vec<ManifestEntry> {
+        if !self.manifest.is_empty() {
+            return self.manifest.clone();
+        }
+        vec![ManifestEntry {
+            name: project.to_string(),
+            path: self.entrypoint.clone(),
+            artefact_type: self.artefact_type.clone(),
+            artefact_hash: self.artefact_hash.clone(),
+            entrypoint: None,
+        }]
+    }
+
+    /// Check the artifacts of the block with [`ManifestEntry::check`], or its
+    /// entrypoint for single artefact blocks.
+    pub fn check_artifacts(&self) -> anyhow::Result<()> {
+        if self.manifest.is_empty() && !is_contained(&self.entrypoint) {
+            anyhow::bail!(
+                "Invalid entrypoint {:?}: must be relative, without `..`",
+                self.entrypoint
+            );
+        }
+        self.manifest.iter().try_for_each(ManifestEntry::check)
+    }
+
+    /// Every store object the block needs: its artefact, and its artifacts when it has a manifest.
+    pub fn object_hashes(&self) -> Vec<&str> {
+        let mut hashes = vec![self.artefact_hash.as_str()];
+        hashes.extend(self.manifest.iter().map(|a| a.artefact_hash.as_str()));
+        hashes
+    }
+
+    /// Bytes covered by `block_hash`, depending on `hash_version`.
+    ///
+    /// * 0: `serde_json` of the original `Meta` layout with an empty `block_hash`,
+    ///   kept so chains packed by earlier builds still verify.
+    /// * 1: the line `anvil-block-v1`, then one `<key>:<len>:<value>` line per hashed
+    ///   field in the order below, where `len` is the byte length of `value`.
+    ///   `created_at` is `<secs>.<nanos>` since the unix epoch and unset optional
+    ///   fields are omitted, so new optional fields can be added without a new version.
+    ///   Recorded environment variables follow as `env.<name>` fields sorted by name,
+    ///   then one `migrated_from` field per previous hash of the block.
+    pub fn hashed_bytes(&self) -> Vec<u8> {
+        if self.hash_version == 0 {
+            let legacy = LegacyMeta {
+                artefact_hash: &self.artefact_hash,
+                artefact_type: &self.artefact_type,
+                created_at: self.created_at,
+                version: &self.version,
+                git_commit: &self.git_commit,
+                prev_block_hash: self.prev_block_hash.as_deref(),
+                block_hash: "",
+                entrypoint: &self.entrypoint,
+            };
+            return serde_json::to_vec(&legacy).unwrap();
+        }
+
+        let created_at = self
+            .created_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let created_at = format!("{}.{:09}", created_at.as_secs(), created_at.subsec_nanos());
+
+        let mut fields = vec![
+            ("artefact_hash", self.artefact_hash.as_str()),
+            ("artefact_type", self.artefact_type.as_str()),
+            ("created_at", created_at.as_str()),
+            ("version", self.version.as_str()),
+            ("git_commit", self.git_commit.as_str()),
+        ];
+        if let Some(prev) = &self.prev_block_hash {
+            fields.push(("prev_block_hash", prev));
+        }
+        fields.push(("entrypoint", &self.entrypoint));
+
+        let p = &self.provenance;
+        let dirty = p.dirty.map(|d| d.to_string());
+        let env: Vec<(String, &str)> = p
+            .env
+            .iter()
+            .map(|(k, v)| (format!("env.{k}"), v.as_str()))
+            .collect();
+        let optional = [
+            ("git_tree_hash", p.git_tree_hash.as_deref()),
+            ("dirty", dirty.as_deref()),
+            ("source_hash", p.source_hash.as_deref()),
+            ("build_command", p.build_command.as_deref()),
+            ("config_hash", p.config_hash.as_deref()),
+            ("host", p.host.as_deref()),
+            ("anvil_version", p.anvil_version.as_deref()),
+        ];
+        fields.extend(optional.into_iter().filter_map(|(k, v)| Some((k, v?))));
+        fields.extend(env.iter().map(|(k, v)| (k.as_str(), *v)));
+        fields.extend(
+            self.migrated_from
+                .iter()
+                .map(|h| ("migrated_from", h.as_str())),
+        );
+
+        let mut out = format!("anvil-block-v{}\n", self.hash_version).into_bytes();
+        for (key, value) in fields {
+            out.extend_from_slice(format!("{key}:{}:", value.len()).as_bytes());
+            out.extend_from_slice(value.as_bytes());
+            out.push(b'\n');
+        }
+        out
+    }
+}
+
+/// Tree hash of `commit` in the repository at `dir`, `None` outside git.
+pub fn get_tree_hash(dir: &Path, commit: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["-C", &dir.to_string_lossy()])
+        .args(["rev-parse", &format!("{commit}^{{tree}}")])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Uncommitted changes and untracked files of the worktree at `dir`, as
+/// `git status --porcelain` lines, `None` outside git.
+///
+/// The chain files written by `pack` itself are not build inputs and are left out.
+pub fn worktree_changes(dir: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(["-C", &dir.to_string_lossy()])
+        .args(["status", "--porcelain", "--"])
+        .args([
+            ":(exclude).anvil/blocks.json",
+            ":(exclude).anvil/blocks.lock",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Whether `dir` is inside a git worktree, `false` when git is not installed.
+pub fn is_git_repo(dir: &Path) -> bool {
+    Command::new("git")
+        .args(["-C", &dir.to_string_lossy()])
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
 }
 
-pub fn get_last_commit() -> anyhow::Result<Option<String>> {
-    let output = Command::new("git").args(["rev-parse", "HEAD"]).output()?;
+/// HEAD of the repository at `dir`, `None` outside a repository, before its
+/// first commit or when git is not installed.
+pub fn get_last_commit(dir: &Path) -> anyhow::Result<Option<String>> {
+    let output = match Command::new("git")
+        .args(["-C", &dir.to_string_lossy()])
+        .args(["rev-parse", "--verify", "-q", "HEAD"])
+        .stderr(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
 
     if !output.status.success() {
         return Ok(None);
@@ -4,11 +4,12 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::store::traits::Store;
+use crate::store::{IndexEntry, meta::Meta, traits::Store};
 
 pub struct MockStore {
     root_path: String,
     artifacts: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    blocks: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl MockStore {
@@ -16,29 +17,103 @@ impl MockStore {
         Self {
             root_path: root_path.into(),
             artifacts: Arc::new(Mutex::new(HashMap::new())),
+            blocks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+
+    /// Overwrite an object without any hash check, to simulate a corrupted store.
+    pub fn corrupt(&self, artefact_hash: &str, bytes: &[u8]) {
+        let mut artifacts = self.artifacts.lock().unwrap();
+        artifacts.insert(artefact_hash.to_string(), bytes.to_vec());
+    }
 }
 
 impl Store for MockStore {
-    fn add_artifact(
-        &self,
-        artifact_byte: &[u8],
-        meta: &crate::store::meta::Meta,
-    ) -> anyhow::Result<()> {
+    fn add_artifact(&self, artifact_byte: &[u8], meta: &Meta) -> anyhow::Result<()> {
+        Self::check_hash(&meta.artefact_hash, artifact_byte)?;
         let mut artefacts = self.artifacts.lock().unwrap();
-        artefacts.insert(meta.block_hash.clone(), artifact_byte.to_vec());
+        artefacts.insert(meta.artefact_hash.clone(), artifact_byte.to_vec());
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.insert(meta.block_hash.clone(), meta.artefact_hash.clone());
         Ok(())
     }
 
-    fn get_artifact(&self, hash: &str) -> anyhow::Result<Vec<u8>> {
+    fn get_artifact(&self, artefact_hash: &str) -> anyhow::Result<Vec<u8>> {
         let artifacts = self.artifacts.lock().unwrap();
-        Ok(artifacts.get(hash).cloned().unwrap_or_default())
+        let data = artifacts
+            .get(artefact_hash)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Artefact {artefact_hash} not found"))?;
+        Self::check_hash(artefact_hash, &data)?;
+        Ok(data)
     }
 
-    fn exists(&self, hash: &str) -> bool {
+    fn exists(&self, artefact_hash: &str) -> bool {
         let artifacts = self.artifacts.lock().unwrap();
-        artifacts.contains_key(hash)
+        artifacts.contains_key(artefact_hash)
+    }
+
+    fn block_artifact(&self, block_hash: &str) -> anyhow::Result<Option<String>> {
+        let blocks = self.blocks.lock().unwrap();
+        Ok(blocks.get(block_hash).cloned())
+    }
+
+    fn put_object(&self, artefact_hash: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        Self::check_hash(artefact_hash, bytes)?;
+        let mut artifacts = self.artifacts.lock().unwrap();
+        artifacts.insert(artefact_hash.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn remove_block(&self, block_hash: &str) -> anyhow::Result<()> {
+        let mut blocks = self.blocks.lock().unwrap();
+        if let Some(artefact_hash) = blocks.remove(block_hash)
+            && !blocks.values().any(|a| *a == artefact_hash)
+        {
+            self.artifacts.lock().unwrap().remove(&artefact_hash);
+        }
+        Ok(())
+    }
+
+    fn list_blocks(&self) -> anyhow::Result<Vec<String>> {
+        let blocks = self.blocks.lock().unwrap();
+        let mut hashes: Vec<String> = blocks.keys().cloned().collect();
+        hashes.sort();
+        Ok(hashes)
+    }
+
+    fn index_entries(&self) -> anyhow::Result<Vec<IndexEntry>> {
+        let blocks = self.blocks.lock().unwrap();
+        Ok(blocks
+            .iter()
+            .map(|(block_hash, artefact_hash)| IndexEntry {
+                project: "mock".to_string(),
+                block_hash: block_hash.clone(),
+                artefact_hash: artefact_hash.clone(),
+            })
+            .collect())
+    }
+
+    fn remove_index_entry(&self, entry: &IndexEntry) -> anyhow::Result<()> {
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.remove(&entry.block_hash);
+        Ok(())
+    }
+
+    fn list_objects(&self) -> anyhow::Result<Vec<(String, u64)>> {
+        let artifacts = self.artifacts.lock().unwrap();
+        let mut objects: Vec<(String, u64)> = artifacts
+            .iter()
+            .map(|(hash, bytes)| (hash.clone(), bytes.len() as u64))
+            .collect();
+        objects.sort();
+        Ok(objects)
+    }
+
+    fn remove_object(&self, artefact_hash: &str) -> anyhow::Result<()> {
+        let mut artifacts = self.artifacts.lock().unwrap();
+        artifacts.remove(artefact_hash);
+        Ok(())
     }
 
     fn root(&self) -> &std::path::Path {
@@ -54,12 +129,4 @@ impl Store for MockStore {
         hasher.update(data);
         format!("{:x}", hasher.finalize())
     }
-
-    fn compute_block_hash(meta: &super::meta::Meta) -> String
-    where
-        Self: Sized,
-    {
-        let json = serde_json::to_string(meta).unwrap();
-        Self::compute_hash(json.as_bytes())
-    }
 }
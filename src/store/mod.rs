@@ -1,6 +1,12 @@
 use std::path::PathBuf;
 
+pub mod archive;
+pub mod atomic;
+pub mod codec;
 pub mod fs_store;
+pub mod http_store;
+pub mod layered;
+pub mod lock;
 pub mod meta;
 pub mod mock;
 pub mod tests;
@@ -10,3 +16,11 @@ pub struct StoreRef {
     pub path: PathBuf,
     pub hash: String,
 }
+
+/// One block recorded in a project index of a store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub project: String,
+    pub block_hash: String,
+    pub artefact_hash: String,
+}
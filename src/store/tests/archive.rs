@@ -0,0 +1,90 @@
+#[test]
+fn test_pack_dir_is_deterministic() {
+    use crate::store::archive::pack_dir;
+    use std::time::{Duration, SystemTime};
+
+    let temp = tempfile::tempdir().unwrap();
+    let build = |name: &str, files: &[&str]| {
+        let dir = temp.path().join(name);
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, file.as_bytes()).unwrap();
+        }
+        dir
+    };
+
+    // same tree, created in another order
+    let a = build("a", &["bin/tool", "lib/x.so", "README"]);
+    let b = build("b", &["README", "lib/x.so", "bin/tool"]);
+    let file = std::fs::File::options()
+        .write(true)
+        .open(b.join("README"))
+        .unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(3600))
+        .unwrap();
+
+    assert_eq!(pack_dir(&a).unwrap(), pack_dir(&b).unwrap());
+
+    std::fs::write(b.join("README"), "changed").unwrap();
+    assert_ne!(pack_dir(&a).unwrap(), pack_dir(&b).unwrap());
+}
+
+#[test]
+fn test_unpack_dir_keeps_executable_bits() {
+    use crate::store::archive::{pack_dir, unpack_dir};
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let src = temp.path().join("src");
+    std::fs::create_dir_all(src.join("bin")).unwrap();
+    std::fs::write(src.join("bin/tool"), "#!/bin/sh").unwrap();
+    std::fs::set_permissions(src.join("bin/tool"), std::fs::Permissions::from_mode(0o700)).unwrap();
+    std::fs::write(src.join("data"), "data").unwrap();
+    std::os::unix::fs::symlink("bin/tool", src.join("tool")).unwrap();
+
+    let dest = temp.path().join("dest");
+    unpack_dir(&pack_dir(&src).unwrap(), &dest).unwrap();
+
+    let mode = |p: &str| {
+        std::fs::metadata(dest.join(p))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777
+    };
+    assert_eq!(mode("bin/tool"), 0o755);
+    assert_eq!(mode("data"), 0o644);
+    assert_eq!(
+        std::fs::read_link(dest.join("tool")).unwrap(),
+        std::path::Path::new("bin/tool")
+    );
+
+    // unpacking again replaces the previous tree
+    std::fs::remove_file(src.join("data")).unwrap();
+    unpack_dir(&pack_dir(&src).unwrap(), &dest).unwrap();
+    assert!(!dest.join("data").exists());
+}
+
+#[test]
+fn test_tree_hash_skips_excluded_paths() {
+    use crate::store::archive::tree_hash;
+    use std::path::PathBuf;
+
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("target")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(dir.join("target/tool"), "v1").unwrap();
+
+    let exclude = [PathBuf::from("target")];
+    let hash = tree_hash(dir, &exclude).unwrap();
+
+    std::fs::write(dir.join("target/tool"), "v2").unwrap();
+    assert_eq!(tree_hash(dir, &exclude).unwrap(), hash);
+    assert_ne!(tree_hash(dir, &[]).unwrap(), hash);
+
+    std::fs::write(dir.join("src/main.rs"), "fn main() { }").unwrap();
+    assert_ne!(tree_hash(dir, &exclude).unwrap(), hash);
+}
@@ -0,0 +1,36 @@
+#[test]
+fn test_atomic_write_replaces_content() {
+    use crate::store::atomic::atomic_write;
+
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("nested/blocks.json");
+
+    atomic_write(&path, b"[]").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"[]");
+
+    atomic_write(&path, b"[{}]").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"[{}]");
+
+    // the temporary file is renamed, never left next to the target
+    assert_eq!(
+        std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+        1
+    );
+}
+
+#[test]
+fn test_failed_atomic_write_keeps_previous_content() {
+    use crate::store::atomic::atomic_write;
+
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("blocks.json");
+    atomic_write(&path, b"previous").unwrap();
+
+    // renaming a file over a non-empty directory fails after the data was written
+    let dir_target = temp.path().join("dir");
+    std::fs::create_dir_all(dir_target.join("child")).unwrap();
+    assert!(atomic_write(&dir_target, b"new").is_err());
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+    assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 2);
+}
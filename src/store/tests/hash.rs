@@ -0,0 +1,106 @@
+#[test]
+fn test_legacy_block_hash_still_verifies() {
+    use crate::store::{fs_store::FsStore, meta::Meta, traits::Store};
+
+    // genesis block of anvil's own chain, packed before hashes were versioned
+    let json = r#"{
+        "artefact_hash": "6dc7020b95eb3da7a409c48e1e6963f0e0c5abad0abf4b3a9b2cbc8f4643563f",
+        "artefact_type": "Bin",
+        "created_at": { "secs_since_epoch": 1764074693, "nanos_since_epoch": 829943405 },
+        "version": "0.1.0",
+        "git_commit": "c00b04bb40796926b65f6179dc480060c4bf0e67",
+        "prev_block_hash": null,
+        "block_hash": "a9df9c3dfafaa1214d8baf642219ae79ad4c497286b37a3041efe884481dc3f8",
+        "entrypoint": "target/release/anvil"
+    }"#;
+    let meta: Meta = serde_json::from_str(json).unwrap();
+
+    assert_eq!(meta.hash_version, 0);
+    assert_eq!(FsStore::compute_block_hash(&meta), meta.block_hash);
+}
+
+#[test]
+fn test_canonical_block_encoding() {
+    use crate::store::meta::{ArtefactType, Meta};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let mut meta = Meta {
+        artefact_hash: "abcd".to_string(),
+        artefact_type: ArtefactType::Bin,
+        created_at: UNIX_EPOCH + Duration::new(1764074693, 5),
+        version: "0.1.0".to_string(),
+        git_commit: "c00b04bb".to_string(),
+        prev_block_hash: Some("a9df".to_string()),
+        block_hash: String::new(),
+        hash_version: 1,
+        manifest: Vec::new(),
+        migrated_from: Vec::new(),
+        provenance: Default::default(),
+        entrypoint: "target/release/anvil".to_string(),
+    };
+
+    let expected = "anvil-block-v1\n\
+        artefact_hash:4:abcd\n\
+        artefact_type:3:Bin\n\
+        created_at:20:1764074693.000000005\n\
+        version:5:0.1.0\n\
+        git_commit:8:c00b04bb\n\
+        prev_block_hash:4:a9df\n\
+        entrypoint:20:target/release/anvil\n";
+    assert_eq!(String::from_utf8(meta.hashed_bytes()).unwrap(), expected);
+
+    // the block hash itself is never part of the hashed bytes
+    meta.block_hash = "filled".to_string();
+    assert_eq!(String::from_utf8(meta.hashed_bytes()).unwrap(), expected);
+}
+
+#[test]
+fn test_provenance_is_hashed() {
+    use crate::store::meta::{ArtefactType, Meta, Provenance};
+    use std::time::UNIX_EPOCH;
+
+    let mut meta = Meta {
+        artefact_hash: "abcd".to_string(),
+        artefact_type: ArtefactType::Bin,
+        created_at: UNIX_EPOCH,
+        version: "0.1.0".to_string(),
+        git_commit: "c00b04bb".to_string(),
+        prev_block_hash: None,
+        block_hash: String::new(),
+        hash_version: 1,
+        manifest: Vec::new(),
+        migrated_from: Vec::new(),
+        provenance: Provenance {
+            git_tree_hash: Some("7e3a".to_string()),
+            dirty: Some(false),
+            source_hash: None,
+            build_command: Some("make".to_string()),
+            config_hash: None,
+            env: [("CC".to_string(), "clang".to_string())].into(),
+            host: Some("x86_64-unknown-linux-gnu".to_string()),
+            anvil_version: Some("0.1.0".to_string()),
+        },
+        entrypoint: "bin".to_string(),
+    };
+
+    let bytes = String::from_utf8(meta.hashed_bytes()).unwrap();
+    assert!(bytes.ends_with(
+        "entrypoint:3:bin\n\
+        git_tree_hash:4:7e3a\n\
+        dirty:5:false\n\
+        build_command:4:make\n\
+        host:24:x86_64-unknown-linux-gnu\n\
+        anvil_version:5:0.1.0\n\
+        env.CC:5:clang\n"
+    ));
+
+    // provenance is stored flat next to the other fields
+    let json = serde_json::to_value(&meta).unwrap();
+    assert_eq!(json["build_command"], "make");
+    assert!(json.get("config_hash").is_none());
+    let parsed: Meta = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.provenance, meta.provenance);
+
+    meta.provenance.dirty = Some(true);
+    assert_ne!(String::from_utf8(meta.hashed_bytes()).unwrap(), bytes);
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// Contents of the in-process server: objects by hash and one index per project.
+#[cfg(test)]
+#[derive(Default)]
+pub struct RemoteState {
+    pub objects: HashMap<String, Vec<u8>>,
+    pub index: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Start a local server speaking the `HttpStore` protocol, returning its base url.
+#[cfg(test)]
+pub fn serve() -> (String, Arc<Mutex<RemoteState>>) {
+    use tiny_http::{Method, Response, Server};
+
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let state = Arc::new(Mutex::new(RemoteState::default()));
+
+    let shared = state.clone();
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = Vec::new();
+            request.as_reader().read_to_end(&mut body).unwrap();
+            let path: Vec<String> = request
+                .url()
+                .trim_matches('/')
+                .split('/')
+                .map(String::from)
+                .collect();
+            let parts: Vec<&str> = path.iter().map(String::as_str).collect();
+
+            let mut state = shared.lock().unwrap();
+            let (status, data) = match (request.method(), parts.as_slice()) {
+                (Method::Get, ["objects"]) => {
+                    let list: Vec<(&String, u64)> = state
+                        .objects
+                        .iter()
+                        .map(|(h, b)| (h, b.len() as u64))
+                        .collect();
+                    (200, serde_json::to_vec(&list).unwrap())
+                }
+                (Method::Get | Method::Head, ["objects", hash]) => match state.objects.get(*hash) {
+                    Some(bytes) => (200, bytes.clone()),
+                    None => (404, Vec::new()),
+                },
+                (Method::Put, ["objects", hash]) => {
+                    state.objects.insert(hash.to_string(), body);
+                    (201, Vec::new())
+                }
+                (Method::Delete, ["objects", hash]) => {
+                    state.objects.remove(*hash);
+                    (204, Vec::new())
+                }
+                (Method::Get, ["index"]) => (200, serde_json::to_vec(&state.index).unwrap()),
+                (Method::Get, ["index", project, block]) => {
+                    match state.index.get(*project).and_then(|i| i.get(*block)) {
+                        Some(hash) => (200, hash.clone().into_bytes()),
+                        None => (404, Vec::new()),
+                    }
+                }
+                (Method::Put, ["index", project, block]) => {
+                    let hash = String::from_utf8(body).unwrap();
+                    state
+                        .index
+                        .entry(project.to_string())
+                        .or_default()
+                        .insert(block.to_string(), hash);
+                    (201, Vec::new())
+                }
+                (Method::Delete, ["index", project, block]) => {
+                    if let Some(index) = state.index.get_mut(*project) {
+                        index.remove(*block);
+                    }
+                    (204, Vec::new())
+                }
+                _ => (404, Vec::new()),
+            };
+            drop(state);
+            let _ = request.respond(Response::from_data(data).with_status_code(status));
+        }
+    });
+
+    (url, state)
+}
+
+#[test]
+fn test_httpstore_add_and_get() {
+    use super::store::meta_for;
+    use crate::store::{http_store::HttpStore, traits::Store};
+
+    let (url, _) = serve();
+    let store = HttpStore::new(&url, "project");
+
+    let meta = meta_for(b"hello world", "block0");
+    assert!(!store.exists(&meta.artefact_hash));
+    store.add_artifact(b"hello world", &meta).unwrap();
+
+    assert!(store.exists(&meta.artefact_hash));
+    assert_eq!(
+        store.get_artifact(&meta.artefact_hash).unwrap(),
+        b"hello world"
+    );
+    assert_eq!(
+        store.block_artifact("block0").unwrap(),
+        Some(meta.artefact_hash.clone())
+    );
+    assert_eq!(store.block_artifact("missing").unwrap(), None);
+    assert_eq!(store.list_blocks().unwrap(), vec!["block0".to_string()]);
+    assert_eq!(store.list_objects().unwrap().len(), 1);
+}
+
+#[test]
+fn test_httpstore_checks_hashes() {
+    use super::store::meta_for;
+    use crate::store::{http_store::HttpStore, traits::Store};
+
+    let (url, state) = serve();
+    let store = HttpStore::new(&url, "project");
+
+    // bytes that do not match the declared hash are never uploaded
+    let meta = meta_for(b"expected", "block-a");
+    assert!(store.add_artifact(b"something else", &meta).is_err());
+    assert!(state.lock().unwrap().objects.is_empty());
+
+    // a tampered object is refused on download
+    store.add_artifact(b"expected", &meta).unwrap();
+    state
+        .lock()
+        .unwrap()
+        .objects
+        .insert(meta.artefact_hash.clone(), b"evil".to_vec());
+    assert!(store.get_artifact(&meta.artefact_hash).is_err());
+}
+
+#[test]
+fn test_httpstore_shares_objects_between_projects() {
+    use super::store::meta_for;
+    use crate::store::{http_store::HttpStore, traits::Store};
+
+    let (url, state) = serve();
+    let a = HttpStore::new(&url, "a");
+    let b = HttpStore::new(&url, "b");
+
+    let meta = meta_for(b"same bytes", "block-a");
+    a.add_artifact(b"same bytes", &meta).unwrap();
+    b.add_artifact(b"same bytes", &meta_for(b"same bytes", "block-b"))
+        .unwrap();
+    assert_eq!(a.index_entries().unwrap().len(), 2);
+
+    // the object stays while project b still refers to it
+    a.remove_block("block-a").unwrap();
+    assert!(a.list_blocks().unwrap().is_empty());
+    assert!(b.exists(&meta.artefact_hash));
+
+    b.remove_block("block-b").unwrap();
+    assert!(state.lock().unwrap().objects.is_empty());
+}
@@ -0,0 +1,87 @@
+#[test]
+fn test_layered_store_falls_through_remotes() {
+    use super::{http::serve, store::meta_for};
+    use crate::store::{
+        http_store::HttpStore, layered::LayeredStore, mock::MockStore, traits::Store,
+    };
+
+    let (first_url, _) = serve();
+    let (second_url, second) = serve();
+    let meta = meta_for(b"remote bytes", "block0");
+    HttpStore::new(&second_url, "project")
+        .add_artifact(b"remote bytes", &meta)
+        .unwrap();
+
+    let store = LayeredStore::new(MockStore::new("cache"))
+        .with_remote(Box::new(HttpStore::new(&first_url, "project")))
+        .with_remote(Box::new(HttpStore::new(&second_url, "project")));
+
+    assert!(store.exists(&meta.artefact_hash));
+    assert!(!store.local().exists(&meta.artefact_hash));
+    assert_eq!(
+        store.block_artifact("block0").unwrap(),
+        Some(meta.artefact_hash.clone())
+    );
+
+    // a remote hit fills the local cache
+    assert_eq!(
+        store.get_artifact(&meta.artefact_hash).unwrap(),
+        b"remote bytes"
+    );
+    assert!(store.local().exists(&meta.artefact_hash));
+
+    // the cache answers even once the remote lost the object
+    second.lock().unwrap().objects.clear();
+    assert_eq!(
+        store.get_artifact(&meta.artefact_hash).unwrap(),
+        b"remote bytes"
+    );
+    assert!(store.get_artifact("missing").is_err());
+}
+
+#[test]
+fn test_layered_store_refuses_tampered_remote() {
+    use super::{http::serve, store::meta_for};
+    use crate::store::{
+        http_store::HttpStore, layered::LayeredStore, mock::MockStore, traits::Store,
+    };
+
+    let (url, state) = serve();
+    let meta = meta_for(b"genuine", "block0");
+    state
+        .lock()
+        .unwrap()
+        .objects
+        .insert(meta.artefact_hash.clone(), b"evil".to_vec());
+
+    let store = LayeredStore::new(MockStore::new("cache"))
+        .with_remote(Box::new(HttpStore::new(&url, "project")));
+    assert!(store.get_artifact(&meta.artefact_hash).is_err());
+    assert!(!store.local().exists(&meta.artefact_hash));
+}
+
+#[test]
+fn test_config_stores_relative_to_config_file() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("tool/.anvil/anvil.yml");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        &path,
+        "project: { name: tool }\n\
+         build: { artifact_dir: ., command: make, incremental: false, jit: false }\n\
+         env: {}\n\
+         stores: [../../shared, /srv/anvil, https://cache.example.com]\n",
+    )
+    .unwrap();
+
+    let config = crate::config::Config::new(Some(&path)).unwrap();
+    let shared = temp.path().join("tool/.anvil/../../shared");
+    assert_eq!(
+        config.stores,
+        [
+            shared.to_string_lossy().as_ref(),
+            "/srv/anvil",
+            "https://cache.example.com"
+        ]
+    );
+}
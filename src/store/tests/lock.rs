@@ -0,0 +1,42 @@
+#[test]
+fn test_lock_times_out_with_holder_pid() {
+    use crate::store::lock::FileLock;
+    use std::time::Duration;
+
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("blocks.lock");
+
+    let held = FileLock::acquire_with_timeout(&path, None).unwrap();
+    let err = FileLock::acquire_with_timeout(&path, Some(Duration::from_millis(100))).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains(&format!("held by pid {}", std::process::id())),
+        "{err}"
+    );
+
+    drop(held);
+    assert!(FileLock::acquire_with_timeout(&path, Some(Duration::from_millis(100))).is_ok());
+}
+
+#[test]
+fn test_store_lock_is_reentrant_and_exclusive() {
+    use crate::store::{fs_store::FsStore, lock::FileLock, traits::Store};
+    use std::time::Duration;
+
+    let temp = tempfile::tempdir().unwrap();
+    let store = FsStore::new(temp.path(), "tool").unwrap();
+    let path = temp.path().join("store.lock");
+
+    let held = store.lock().unwrap();
+    // writes of the holder take the lock again
+    let again = store.lock().unwrap();
+    store
+        .put_object(&FsStore::compute_hash(b"object"), b"object")
+        .unwrap();
+    drop(again);
+
+    // other processes wait until every guard is dropped
+    assert!(FileLock::acquire_with_timeout(&path, Some(Duration::from_millis(100))).is_err());
+    drop(held);
+    assert!(FileLock::acquire_with_timeout(&path, Some(Duration::from_millis(100))).is_ok());
+}
@@ -1 +1,7 @@
+pub mod archive;
+pub mod atomic;
+pub mod hash;
+pub mod http;
+pub mod layered;
+pub mod lock;
 pub mod store;
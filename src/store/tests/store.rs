@@ -1,30 +1,236 @@
 #[test]
 fn test_fsstore_add_and_get() {
-    use crate::store::{
-        fs_store::FsStore,
-        meta::{ArtefactType, Meta},
-        traits::Store,
-    };
-    use std::time::SystemTime;
+    use crate::store::{fs_store::FsStore, traits::Store};
     let temp_dir = tempfile::tempdir().unwrap();
-    let store = FsStore::new(temp_dir.path()).unwrap();
+    let store = FsStore::new(temp_dir.path(), "project").unwrap();
 
     let data = b"hello world";
     let hash = FsStore::compute_hash(data);
 
-    let meta = Meta {
-        artefact_hash: hash.clone(),
+    store.add_artifact(data, &meta_for(data, &hash)).unwrap();
+    let read = store.get_artifact(&hash).unwrap();
+
+    assert_eq!(data.to_vec(), read);
+}
+
+#[test]
+fn test_fsstore_list_and_remove_blocks() {
+    use crate::store::{fs_store::FsStore, traits::Store};
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = FsStore::new(temp_dir.path(), "project").unwrap();
+
+    for (i, data) in [b"first", b"other"].iter().enumerate() {
+        store
+            .add_artifact(*data, &meta_for(*data, &format!("block{i}")))
+            .unwrap();
+    }
+
+    assert_eq!(store.list_blocks().unwrap(), vec!["block0", "block1"]);
+
+    store.remove_block("block0").unwrap();
+    assert_eq!(store.block_artifact("block0").unwrap(), None);
+    assert!(!store.exists(&FsStore::compute_hash(b"first")));
+    assert_eq!(store.list_blocks().unwrap(), vec!["block1"]);
+
+    // removing a missing block is not an error
+    store.remove_block("block0").unwrap();
+}
+
+#[cfg(test)]
+pub(super) fn meta_for(data: &[u8], block_hash: &str) -> crate::store::meta::Meta {
+    use crate::store::{fs_store::FsStore, meta::ArtefactType, traits::Store};
+    crate::store::meta::Meta {
+        artefact_hash: FsStore::compute_hash(data),
         artefact_type: ArtefactType::Bin,
-        created_at: SystemTime::now(),
+        created_at: std::time::SystemTime::now(),
         git_commit: "abc1234".to_string(),
         prev_block_hash: None,
-        block_hash: hash.clone(),
+        block_hash: block_hash.to_string(),
+        hash_version: 1,
+        manifest: Vec::new(),
+        migrated_from: Vec::new(),
+        provenance: Default::default(),
         entrypoint: "test".to_string(),
         version: "0.0.1".to_string(),
+    }
+}
+
+#[test]
+fn test_fsstore_shares_objects_between_projects() {
+    use crate::store::{fs_store::FsStore, traits::Store};
+    let temp_dir = tempfile::tempdir().unwrap();
+    let first = FsStore::new(temp_dir.path(), "first").unwrap();
+    let second = FsStore::new(temp_dir.path(), "second").unwrap();
+
+    let data = b"same bytes";
+    let meta = meta_for(data, "block-a");
+    first.add_artifact(data, &meta).unwrap();
+    second
+        .add_artifact(data, &meta_for(data, "block-b"))
+        .unwrap();
+
+    let object = first.object_path(&meta.artefact_hash);
+    assert!(
+        object.starts_with(
+            temp_dir
+                .path()
+                .join("objects")
+                .join(&meta.artefact_hash[..2])
+        )
+    );
+    assert!(object.exists());
+
+    // each project only sees its own blocks
+    assert_eq!(first.list_blocks().unwrap(), vec!["block-a"]);
+    assert_eq!(first.block_artifact("block-b").unwrap(), None);
+    assert_eq!(
+        second.block_artifact("block-b").unwrap(),
+        Some(meta.artefact_hash.clone())
+    );
+
+    // the object is kept as long as one project still references it
+    first.remove_block("block-a").unwrap();
+    assert!(object.exists());
+    second.remove_block("block-b").unwrap();
+    assert!(!object.exists());
+}
+
+#[test]
+fn test_fsstore_migrates_project_stores() {
+    use crate::store::{fs_store::FsStore, traits::Store};
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let block_hash = FsStore::compute_hash(b"block");
+    let legacy = temp_dir.path().join("legacy");
+    std::fs::create_dir_all(&legacy).unwrap();
+    std::fs::write(legacy.join(&block_hash), b"old artefact").unwrap();
+
+    let store = FsStore::new(temp_dir.path(), "legacy").unwrap();
+    assert!(legacy.exists());
+    store.migrate_project_stores().unwrap();
+
+    let artefact_hash = FsStore::compute_hash(b"old artefact");
+    assert!(!legacy.exists());
+    assert_eq!(
+        store.block_artifact(&block_hash).unwrap(),
+        Some(artefact_hash.clone())
+    );
+    assert_eq!(store.get_artifact(&artefact_hash).unwrap(), b"old artefact");
+}
+
+#[test]
+fn test_fsstore_keeps_foreign_directories() {
+    use crate::store::{fs_store::FsStore, traits::Store};
+    let temp_dir = tempfile::tempdir().unwrap();
+
+    let secret = temp_dir.path().join("secret");
+    std::fs::create_dir_all(&secret).unwrap();
+    std::fs::write(secret.join("id_ed25519"), b"key").unwrap();
+    let mixed = temp_dir.path().join("mixed");
+    std::fs::create_dir_all(&mixed).unwrap();
+    std::fs::write(mixed.join(FsStore::compute_hash(b"block")), b"bytes").unwrap();
+    std::fs::write(mixed.join("notes.txt"), b"mine").unwrap();
+
+    let store = FsStore::new(temp_dir.path(), "secret").unwrap();
+    store.migrate_project_stores().unwrap();
+
+    assert_eq!(std::fs::read(secret.join("id_ed25519")).unwrap(), b"key");
+    assert_eq!(std::fs::read_dir(&mixed).unwrap().count(), 2);
+    assert!(store.list_blocks().unwrap().is_empty());
+}
+
+#[test]
+fn test_fsstore_checks_hashes() {
+    use crate::store::{fs_store::FsStore, traits::Store};
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = FsStore::new(temp_dir.path(), "project").unwrap();
+
+    // bytes that do not match the declared artefact hash are refused
+    let meta = meta_for(b"expected", "block-a");
+    assert!(store.add_artifact(b"something else", &meta).is_err());
+    assert!(!store.exists(&meta.artefact_hash));
+
+    // identical bytes under two blocks are stored once
+    store.add_artifact(b"expected", &meta).unwrap();
+    store
+        .add_artifact(b"expected", &meta_for(b"expected", "block-b"))
+        .unwrap();
+    let shard = store.object_path(&meta.artefact_hash);
+    assert_eq!(
+        std::fs::read_dir(shard.parent().unwrap()).unwrap().count(),
+        1
+    );
+
+    // a corrupted object is detected on read
+    std::fs::write(&shard, b"bit rot").unwrap();
+    assert!(store.get_artifact(&meta.artefact_hash).is_err());
+}
+
+#[test]
+fn test_fsstore_compresses_objects() {
+    use crate::{
+        config::{Codec, Compression},
+        store::{fs_store::FsStore, traits::Store},
     };
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data = vec![b'a'; 64 * 1024];
+    let meta = meta_for(&data, "block-a");
 
-    store.add_artifact(data, &meta).unwrap();
-    let read = store.get_artifact(&hash).unwrap();
+    let store = FsStore::new(temp_dir.path().join("zstd"), "project").unwrap();
+    store.add_artifact(&data, &meta).unwrap();
+    let on_disk = std::fs::metadata(store.object_path(&meta.artefact_hash)).unwrap();
+    assert!(on_disk.len() < 1024);
+    assert_eq!(store.get_artifact(&meta.artefact_hash).unwrap(), data);
 
-    assert_eq!(data.to_vec(), read);
+    let raw = FsStore::new(temp_dir.path().join("raw"), "project")
+        .unwrap()
+        .with_compression(Compression {
+            codec: Codec::None,
+            level: 0,
+        });
+    raw.add_artifact(&data, &meta).unwrap();
+    let on_disk = std::fs::metadata(raw.object_path(&meta.artefact_hash)).unwrap();
+    assert!(on_disk.len() > data.len() as u64);
+    assert_eq!(raw.get_artifact(&meta.artefact_hash).unwrap(), data);
+
+    // objects written before compression existed have no header
+    std::fs::write(raw.object_path(&meta.artefact_hash), &data).unwrap();
+    assert_eq!(raw.get_artifact(&meta.artefact_hash).unwrap(), data);
+}
+
+#[test]
+fn test_fsstore_finds_manifests_by_their_first_byte() {
+    use crate::{
+        config::{Codec, Compression},
+        store::{fs_store::FsStore, meta::Manifest, traits::Store},
+    };
+    let temp_dir = tempfile::tempdir().unwrap();
+    let binary = vec![b'a'; 64 * 1024];
+    let manifest = serde_json::to_vec(&Manifest::new(&[])).unwrap();
+
+    for codec in [Codec::Zstd, Codec::None] {
+        let store = FsStore::new(temp_dir.path().join(format!("{codec:?}")), "project")
+            .unwrap()
+            .with_compression(Compression { codec, level: 3 });
+        store
+            .add_artifact(&binary, &meta_for(&binary, "bin"))
+            .unwrap();
+        store
+            .add_artifact(&manifest, &meta_for(&manifest, "manifest"))
+            .unwrap();
+
+        let found = store
+            .get_manifest(&FsStore::compute_hash(&manifest))
+            .unwrap();
+        assert!(found.is_some_and(|m| m.artifacts.is_empty()));
+
+        // other objects are not read whole, so a corrupted one goes unnoticed
+        let path = store.object_path(&FsStore::compute_hash(&binary));
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(b"trailing garbage");
+        std::fs::write(&path, bytes).unwrap();
+        assert!(store.get_artifact(&FsStore::compute_hash(&binary)).is_err());
+        let found = store.get_manifest(&FsStore::compute_hash(&binary)).unwrap();
+        assert!(found.is_none());
+    }
 }
@@ -1,13 +1,49 @@
 use anyhow::Result;
 use sha2::{Digest, Sha256};
 
-use crate::store::meta::Meta;
+use crate::store::{
+    IndexEntry,
+    lock::StoreLock,
+    meta::{Manifest, Meta},
+};
 
+/// Objects are addressed by their artefact hash, blocks only point at them.
 pub trait Store {
+    /// Store `artifact_byte` under `meta.artefact_hash` and record it as the artefact
+    /// of `meta.block_hash`. Fails if the bytes do not match `meta.artefact_hash`.
     fn add_artifact(&self, artifact_byte: &[u8], meta: &Meta) -> Result<()>;
-    fn get_artifact(&self, hash: &str) -> Result<Vec<u8>>;
-    fn exists(&self, hash: &str) -> bool;
+    /// Bytes of the object `artefact_hash`, checked against the hash before being returned.
+    fn get_artifact(&self, artefact_hash: &str) -> Result<Vec<u8>>;
+    /// The object `artefact_hash` parsed as a manifest, `None` for any other object.
+    fn get_manifest(&self, artefact_hash: &str) -> Result<Option<Manifest>> {
+        Ok(Manifest::parse(&self.get_artifact(artefact_hash)?))
+    }
+    fn exists(&self, artefact_hash: &str) -> bool;
+    /// Whether `artefact_hash` is stored here, without asking any remote store.
+    fn exists_locally(&self, artefact_hash: &str) -> bool {
+        self.exists(artefact_hash)
+    }
+    /// Artefact hash recorded for `block_hash`, if the block is known to this store.
+    fn block_artifact(&self, block_hash: &str) -> Result<Option<String>>;
+    /// Store `bytes` under `artefact_hash` without recording any block. Fails if the
+    /// bytes do not match `artefact_hash`.
+    fn put_object(&self, artefact_hash: &str, bytes: &[u8]) -> Result<()>;
+    /// Forget `block_hash`, and its object once no other block refers to it.
+    fn remove_block(&self, block_hash: &str) -> Result<()>;
+    fn list_blocks(&self) -> Result<Vec<String>>;
+    /// Blocks indexed by every project sharing this store.
+    fn index_entries(&self) -> Result<Vec<IndexEntry>>;
+    fn remove_index_entry(&self, entry: &IndexEntry) -> Result<()>;
+    /// Every stored object with its size on disk.
+    fn list_objects(&self) -> Result<Vec<(String, u64)>>;
+    fn remove_object(&self, artefact_hash: &str) -> Result<()>;
     fn root(&self) -> &std::path::Path;
+    /// Keep other processes from writing to or collecting the store until the guard
+    /// is dropped, for writes that must not interleave with a `gc`, like objects
+    /// put before the block indexing them. Taking it again while held is fine.
+    fn lock(&self) -> Result<StoreLock> {
+        Ok(StoreLock::default())
+    }
 
     fn compute_hash(data: &[u8]) -> String
     where
@@ -23,7 +59,17 @@ pub trait Store {
     where
         Self: Sized,
     {
-        let json = serde_json::to_string(meta).unwrap();
-        Self::compute_hash(json.as_bytes())
+        Self::compute_hash(&meta.hashed_bytes())
+    }
+
+    fn check_hash(expected: &str, data: &[u8]) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let actual = Self::compute_hash(data);
+        if actual != expected {
+            anyhow::bail!("Artefact integrity check failed: expected {expected}, found {actual}");
+        }
+        Ok(())
     }
 }